@@ -1,13 +1,34 @@
+use std::collections::BTreeSet;
 use std::env;
-use std::sync::{Arc};
-use std::time::Duration;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
 use colored::{ColoredString, Colorize};
+use futures::future::join_all;
 use cli_table::{ Cell, Style, Table};
-use tokio::sync::{mpsc, Mutex};
-use crate::scanning::tcp::{get_user_agents, scan_tcp, scan_udp};
+use webshot::scanning::tcp::{get_user_agents, print_result};
+use webshot::scanning::dns::{expand_targets, target_kind, AxfrResult, IpType, TargetKind, DEFAULT_MAX_HOSTS};
+use webshot::scanning::checkpoint::ProgressDb;
+use webshot::scanning::analysis::{likely_domain_controller, looks_like_honeypot, RepeatStats, DEFAULT_HONEYPOT_THRESHOLD};
+use webshot::scanning::protocols::database::{self, DatabaseType};
+use webshot::scanning::protocols::finger::FingerUserProbe;
+use webshot::scanning::protocols::http::{check_web, WEB_PORTS};
+use webshot::scanning::protocols::{load_client_first, PayloadProbe, ServiceProbe};
+use webshot::scanning::plugins::PluginRegistry;
+use webshot::scanning::top_ports::TOP_PORTS;
+use webshot::scanning::profiles::{self, parse_ports, port_set, Profile, DEFAULT_PORT_SET};
+use webshot::scanning::proxy::Proxy;
+use webshot::scanning::scanner::{endpoint_label, NetworkScanner, ScanConfig};
+use webshot::scanning::output::{push_to_gateway, CsvWriter, Destination, JsonWriter, OutputWriter, PortsCsvWriter, SqliteWriter, TextWriter, XmlWriter, STATUS_TO_STDERR};
+use webshot::status;
+use webshot::scanning::types::{diff_results, merge_results, ResultChange, ScanResult, ScanSummary, WebProbeResult};
 use chrono::Local;
+use crate::utils::select_banner;
+use webshot::scanning;
+use rand::seq::SliceRandom;
+use regex::Regex;
 
-mod scanning;
+mod utils;
 
 #[tokio::main]
 async fn main() {
@@ -15,7 +36,7 @@ async fn main() {
 
     if args.len() < 2 {
         println!("{}", "Command not found. Use --help for more information".red());
-        eprintln!("{}", "Usage: [params] (<ip> || <url>) <port>".red());
+        eprintln!("{}", "Usage: [params] (<ip> || <cidr> || <url>)[,...] <port>".red());
         return;
     }
 
@@ -23,13 +44,56 @@ async fn main() {
         let table = vec![
             vec!["--help".green(), ColoredString::from("Show this help message")],
             vec!["<ip> | <url>".green(), ColoredString::from("IP address to scan or URL")],
-            vec!["<port>".green(), ColoredString::from("Port to scan (e.g. 80) default: 1-9999")],
-            vec!["--all".green(), ColoredString::from("Scan all ports (1-65535)")],
+            vec!["<port>".green(), ColoredString::from("Port, range or list to scan (e.g. 80, 1-1024, 22,80,8000-8100) default: 1-1024")],
+            vec!["--all".green(), ColoredString::from("Scan all ports (1-65535) when no port is given")],
+            vec!["--top-ports <n>".green(), ColoredString::from("Scan the n most commonly open ports (1-1000) instead of a port range (env: WEBSHOT_TOP_PORTS)")],
+            vec!["--default-ports <set>".green(), ColoredString::from("Ports scanned when none are given: fast (16 common services), common (1-1024, default) or full (1-65535) (env: WEBSHOT_DEFAULT_PORTS)")],
             vec!["--tcp".green(), ColoredString::from("Scan only TCP ports")],
+            vec!["--scan-type <type>".green(), ColoredString::from("connect (default, and currently the only type): full TCP handshake, no privileges needed but logged by the target (env: WEBSHOT_SCAN_TYPE)")],
+            vec!["--tcp-connect-only".green(), ColoredString::from("Same as --scan-type connect")],
             vec!["--udp".green(), ColoredString::from("Scan only UDP ports")],
-            vec!["--random-agent".green(), ColoredString::from("Use a random user agent")],
+            vec!["--random-agent".green(), ColoredString::from("Send a User-Agent picked at random from user-agents.txt with HTTP probes")],
+            vec!["--user-agents-file <file>".green(), ColoredString::from("Pick the --random-agent user agent from this file, one per line (env: WEBSHOT_USER_AGENTS_FILE)")],
+            vec!["--print-ascii-art".green(), ColoredString::from("Print the webshot ASCII art on startup (it changes with the season)")],
+            vec!["--quiet".green(), ColoredString::from("Skip the startup banner and notices")],
             vec!["--ipv6".green(), ColoredString::from("Scan for IPv6 addresses")],
+            vec!["--max-hosts <n>".green(), ColoredString::from("Most hosts CIDR blocks and target lists may expand to, 65536 by default (env: WEBSHOT_MAX_HOSTS)")],
             vec!["--ipv4".green(), ColoredString::from("Scan for IPv4 addresses (default)")],
+            vec!["--unix-socket <path>".green(), ColoredString::from("Grab the banner of a local UNIX domain socket (e.g. /var/run/docker.sock) instead of scanning a host")],
+            vec!["mdns-discover [--timeout <secs>]".green(), ColoredString::from("Discover mDNS/Bonjour services on the local network (default: 30s)")],
+            vec!["replay --pcap <file>".green(), ColoredString::from("Rebuild open ports and banners from a pcap capture instead of scanning")],
+            vec!["web <url|host> [--json]".green(), ColoredString::from("Check the common web ports (80, 443, 8080, 8443, 3000, 5000, 8000) for HTTP and HTTPS")],
+            vec!["database <type> <target> [--port <port>]".green(), ColoredString::from("Fingerprint a mysql, postgres, redis or mongodb server with its own handshake, or all four with type all")],
+            vec!["--profile <name>".green(), ColoredString::from("Use a named preset of protocol, ports and timing: quick, web, db or one from --profiles-file (env: WEBSHOT_PROFILE)")],
+            vec!["--profiles-file <file>".green(), ColoredString::from("JSON file with custom profiles (env: WEBSHOT_PROFILES_FILE)")],
+            vec!["--timeout <secs>".green(), ColoredString::from("Per-port timeout in seconds (default: 100, env: WEBSHOT_TIMEOUT)")],
+            vec!["--min-banner-bytes <n>".green(), ColoredString::from("Responses shorter than n bytes are not treated as a banner (default: 1, env: WEBSHOT_MIN_BANNER_BYTES)")],
+            vec!["--try-axfr".green(), ColoredString::from("Try a DNS zone transfer against the target domain's nameservers (active check)")],
+            vec!["--finger-user <name>".green(), ColoredString::from("Query this user on Finger (port 79) instead of listing the logged on users (env: WEBSHOT_FINGER_USER)")],
+            vec!["--test-connectivity".green(), ColoredString::from("Connect to the first port before scanning and warn when the target cannot be reached")],
+            vec!["--half-open".green(), ColoredString::from("Close scan connections with a RST instead of a FIN (SO_LINGER 0)")],
+            vec!["--retry-empty-banner".green(), ColoredString::from("Reconnect once with a generic probe when an open port sends no banner")],
+            vec!["--verbose-closed".green(), ColoredString::from("Print every closed TCP port with the reason: refused, timeout, unreachable or reset")],
+            vec!["--ports-csv".green(), ColoredString::from("Print the open ports as a comma-separated list (e.g. 22,80,443)")],
+            vec!["--ports-csv-proto".green(), ColoredString::from("Same as --ports-csv with a protocol suffix (e.g. 22/tcp,80/tcp)")],
+            vec!["--xml".green(), ColoredString::from("Print the results as nmap XML (-oX) for tools that read .nmap.xml files")],
+            vec!["--csv".green(), ColoredString::from("Print one CSV row per open port: port, protocol, state, service, banner, target_ip, hostname, timestamp")],
+            vec!["-o, --output <file>".green(), ColoredString::from("Write the results to a file instead of stdout, as XML for .xml, CSV for .csv, text otherwise (env: WEBSHOT_OUTPUT)")],
+            vec!["--progress-db <file>".green(), ColoredString::from("Record every finished port in a SQLite database and skip the ports it already holds when the scan is restarted (env: WEBSHOT_PROGRESS_DB)")],
+            vec!["--push-gateway <url>".green(), ColoredString::from("Push the scan totals to a Prometheus Pushgateway when the scan completes (env: WEBSHOT_PUSH_GATEWAY)")],
+            vec!["--sqlite <file>".green(), ColoredString::from("Append the scan and its open ports to a SQLite database (env: WEBSHOT_SQLITE)")],
+            vec!["--http-proxy <url>".green(), ColoredString::from("Tunnel TCP scans through an http:// (CONNECT) or socks5:// proxy (env: WEBSHOT_HTTP_PROXY)")],
+            vec!["--nmap-probes <file>".green(), ColoredString::from("nmap-service-probes file used to name services from their banners instead of the bundled one (env: WEBSHOT_NMAP_PROBES)")],
+            vec!["--probes <file>".green(), ColoredString::from("JSON object of port => payload sent before reading the banner, replacing the built-in probes for those ports (env: WEBSHOT_PROBES)")],
+            vec!["--chunk-size <n>".green(), ColoredString::from("Scan ports in batches of n, one batch at a time (env: WEBSHOT_CHUNK_SIZE)")],
+            vec!["--concurrency <n>".green(), ColoredString::from("Scan at most n ports at the same time, 500 by default (env: WEBSHOT_CONCURRENCY)")],
+            vec!["--rate-limit <pps>".green(), ColoredString::from("Start at most pps port scans per second, e.g. to stay under IDS thresholds (env: WEBSHOT_RATE_LIMIT)")],
+            vec!["--retries <n>".green(), ColoredString::from("Retry a port that did not answer n times with exponential backoff, default 0 for TCP and 2 for UDP (env: WEBSHOT_RETRIES)")],
+            vec!["--banner-regex <regex>".green(), ColoredString::from("Only report open ports whose banner matches the regex (env: WEBSHOT_BANNER_REGEX)")],
+            vec!["--banner-grep <pattern>".green(), ColoredString::from("Like --banner-regex, and highlights the matching text. Invalid regexes are matched as plain text (env: WEBSHOT_BANNER_GREP)")],
+            vec!["--watch <seconds>".green(), ColoredString::from("Rescan every interval and print only opened, closed and changed ports until Ctrl+C (env: WEBSHOT_WATCH)")],
+            vec!["--repeat <n>".green(), ColoredString::from("Run the scan n times and report timing and consistency (env: WEBSHOT_REPEAT)")],
+            vec!["--honeypot-threshold <ratio>".green(), ColoredString::from("Share of open ports with similar banners that flags a honeypot (default: 0.9, env: WEBSHOT_HONEYPOT_THRESHOLD)")],
         ]
             .table()
             .title(vec![
@@ -43,92 +107,838 @@ async fn main() {
         println!("{}", "webshot 192.168.1.1 80-443 --all".green());
         println!("{}", "webshot 192.168.1.1 80-443".green());
         println!("{}", "webshot 192.168.1.1 --tcp".green());
+        println!("{}", "webshot 192.168.1.1 --profile web".green());
+        println!("{}", "webshot 192.168.1.0/24 22-443".green());
+        println!("{}", "webshot 10.0.0.1,10.0.0.5,example.com 80".green());
+        println!("{}", "webshot mdns-discover --timeout 10".green());
+        println!("{}", "webshot replay --pcap scan.pcap".green());
+        println!("{}", "webshot database redis 192.168.1.10".green());
+        println!("{}", "webshot web https://example.com --json".green());
+        println!("{}", "webshot --unix-socket /var/run/docker.sock --timeout 5".green());
         return;
     }
 
     let time = Local::now().format("%H:%M:%S").to_string();
 
+    let output = arg_value(&args, "--output", "WEBSHOT_OUTPUT").or_else(|| arg_value(&args, "-o", "WEBSHOT_OUTPUT")).map(PathBuf::from);
+    // A machine-readable format printed to stdout has to be the only thing there; with
+    // --output the results go to the file, and everything else to stderr
+    if output.is_some() || ["--xml", "--csv", "--ports-csv", "--ports-csv-proto"].iter().any(|flag| args.contains(&flag.to_string())) {
+        STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+    }
+    if args.contains(&"--json".to_string()) {
+        if args[1] != "web" {
+            eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "--json only applies to the web subcommand, write scan results as JSON with -o <file>.json".red());
+            return;
+        }
+        STATUS_TO_STDERR.store(true, Ordering::Relaxed);
+    }
+
     let mut ip = String::new();
-    println!("{}{} {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), "Webshot 0.1.0. Webshot must not be used for illegal purposes. Webshot developers are not responsible for any illegal activity.".yellow());
-    println!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), "Webshot is open source to support: https://github.com/yasinldev/webshot".blue());
-    println!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), "Webshot uses different user agents to scan. Using a random user agent...".blue());
+    if !args.contains(&"--quiet".to_string()) {
+        if args.contains(&"--print-ascii-art".to_string()) {
+            status!("{}", select_banner(Local::now().date_naive()).green());
+        }
+        status!("{}{} {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), "Webshot 0.1.0. Webshot must not be used for illegal purposes. Webshot developers are not responsible for any illegal activity.".yellow());
+        status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), "Webshot is open source to support: https://github.com/yasinldev/webshot".blue());
+        if args.contains(&"--random-agent".to_string()) {
+            status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), "Webshot uses different user agents to scan. Using a random user agent...".blue());
+        }
+    }
 
-    let ip_type = scanning::dns::resolve_domain(&args[1]).await;
+    if args[1] == "mdns-discover" {
+        let listen = match args.iter().position(|arg| arg == "--timeout").and_then(|index| args.get(index + 1)) {
+            Some(value) => match value.trim_end_matches('s').parse::<u64>() {
+                Ok(secs) if secs > 0 => Duration::from_secs(secs),
+                _ => {
+                    eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid timeout (expected a positive number of seconds)".red());
+                    return;
+                }
+            },
+            None => Duration::from_secs(30),
+        };
 
-    let mut ports: Vec<u16> = Vec::new();
-    if args.len() > 2 {
-        if args[2].contains("-") {
-            ports = args[2].split('-').map(|s| s.parse().unwrap()).collect();
+        status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Listening for mDNS services for {}s...", listen.as_secs()).blue());
+        match scanning::mdns::discover(listen).await {
+            Ok(services) => status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Discovered {} mDNS services", services.len()).green()),
+            Err(e) => eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "mDNS discovery failed".red(), e),
+        }
+        return;
+    }
 
-            if ports.len() != 2 || ports[0] > ports[1] {
-                eprintln!("{}{} {}", format!("[{}]", time), "[ERROR]".on_red(), "Invalid Port Range".red());
+    if let Some(path) = arg_value(&args, "--nmap-probes", "WEBSHOT_NMAP_PROBES") {
+        match scanning::nmap::load(&path) {
+            Ok(count) => status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Loaded {} service match lines from {}", count, path).blue()),
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid nmap service probes file".red(), e);
                 return;
             }
-        } else {
-            ports.push(args[2].parse().unwrap());
         }
     }
 
-    if args.contains(&"--ipv6".to_string()) {
-        if let Some(ipv6) = ip_type.ipv6 {
-            ip = match ipv6 {
-                scanning::dns::IpType::V6(ip) => ip,
-                _ => String::new(),
-            };
+    if args[1] == "replay" {
+        let Some(path) = args.iter().position(|arg| arg == "--pcap").and_then(|index| args.get(index + 1)) else {
+            eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "replay needs a capture file: webshot replay --pcap <file>".red());
+            return;
+        };
+
+        match scanning::replay::replay(path).await {
+            Ok(hosts) => {
+                for (host, results) in &hosts {
+                    status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("{}: {} open ports", host, results.len()).blue());
+                    for result in results {
+                        print_result(result, None, false);
+                    }
+                    for (plugin, finding) in PluginRegistry::with_builtin().run(results) {
+                        let port = finding.port.map(|port| port.to_string()).unwrap_or_default();
+                        status!("{}{} {} => {}", finding.severity.label(), format!("[{}]", plugin).yellow(), port.yellow(), finding.message);
+                    }
+                }
+                status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Replayed {} hosts from {}", hosts.len(), path).green());
+            }
+            Err(e) => eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Replay failed".red(), e),
+        }
+        return;
+    }
+
+    let profile = match arg_value(&args, "--profile", "WEBSHOT_PROFILE") {
+        Some(name) => match profiles::resolve(&name, arg_value(&args, "--profiles-file", "WEBSHOT_PROFILES_FILE").as_deref()) {
+            Ok(profile) => profile,
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid profile".red(), e);
+                return;
+            }
+        },
+        None => Profile::default(),
+    };
+
+    let timeout = match arg_value(&args, "--timeout", "WEBSHOT_TIMEOUT") {
+        Some(value) => match value.parse::<u64>() {
+            Ok(secs) if secs > 0 => Duration::from_secs(secs),
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid timeout (expected a positive number of seconds)".red());
+                return;
+            }
+        },
+        None => profile.timeout.unwrap_or(Duration::from_secs(100)),
+    };
+
+    if args[1] == "--unix-socket" {
+        let Some(path) = args.get(2).filter(|path| !path.starts_with("--")) else {
+            eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Missing socket path".red());
+            return;
+        };
+
+        if scanning::tcp::scan_unix_socket(path, timeout).await.is_none() {
+            eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Socket not reachable".red(), path);
+        }
+        return;
+    }
+
+    if args[1] == "database" {
+        let Some(db_type) = args.get(2).filter(|name| !name.starts_with("--")) else {
+            eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "database needs a type and a target: webshot database <type> <target> [--port <port>]".red());
+            return;
+        };
+        let db_types = match DatabaseType::parse(db_type) {
+            Ok(db_types) => db_types,
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid database type".red(), e);
+                return;
+            }
+        };
+        let Some(target) = args.get(3).filter(|target| !target.starts_with("--")) else {
+            eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Missing database target".red());
+            return;
+        };
+        let port = match args.iter().position(|arg| arg == "--port").and_then(|index| args.get(index + 1)) {
+            Some(_) if db_types.len() > 1 => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "--port needs a single database type".red());
+                return;
+            }
+            Some(value) => match value.parse::<u16>() {
+                Ok(port) if port > 0 => Some(port),
+                _ => {
+                    eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid port".red(), value);
+                    return;
+                }
+            },
+            None => None,
+        };
+
+        let addresses = match scanning::dns::resolve_domain(target).await {
+            Ok(addresses) => addresses,
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Could not resolve target".red(), e);
+                return;
+            }
+        };
+        let host = match addresses.ipv4.or(addresses.ipv6) {
+            Some(IpType::V4(ip)) | Some(IpType::V6(ip)) => ip,
+            None => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "No address found".red(), target);
+                return;
+            }
+        };
+
+        let host = host.as_str();
+        let checks = db_types.iter().map(|&db_type| {
+            let port = port.unwrap_or(db_type.default_port());
+            async move { (db_type, port, database::check(db_type, host, port, timeout).await) }
+        });
+        for (db_type, port, outcome) in join_all(checks).await {
+            match outcome {
+                Ok(Some(result)) => print_result(&result.into_scan_result(port), None, false),
+                Ok(None) => status!("{}{} {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), format!("Port {} is open but did not answer like {:?}", port, db_type).yellow()),
+                Err(e) => eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), format!("Could not connect to port {}", port).red(), e),
+            }
         }
+        return;
+    }
+
+    if args[1] == "web" {
+        let Some(target) = args.get(2).filter(|target| !target.starts_with("--")) else {
+            eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "web needs a target: webshot web <url|host> [--json]".red());
+            return;
+        };
+        let host_header = match target_kind(target) {
+            TargetKind::Ip(ip) => ip.to_string(),
+            TargetKind::Domain(name) => name,
+            _ => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid target".red(), target);
+                return;
+            }
+        };
+
+        let addresses = match scanning::dns::resolve_domain(target).await {
+            Ok(addresses) => addresses,
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Could not resolve target".red(), e);
+                return;
+            }
+        };
+        let ip = match addresses.ipv4.or(addresses.ipv6) {
+            Some(IpType::V4(ip)) | Some(IpType::V6(ip)) => ip,
+            None => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "No address found".red(), target);
+                return;
+            }
+        };
+
+        let checks = WEB_PORTS.iter().map(|&port| check_web(&host_header, &ip, port, timeout));
+        let results: Vec<WebProbeResult> = join_all(checks).await.into_iter().flatten().collect();
+
+        if args.contains(&"--json".to_string()) {
+            let json: Vec<_> = results.iter().map(WebProbeResult::to_json).collect();
+            println!("{}", serde_json::to_string_pretty(&json).unwrap());
+        } else if results.is_empty() {
+            status!("{}{} {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), format!("No web service answered on {}", host_header).yellow());
+        } else {
+            let rows: Vec<_> = results
+                .iter()
+                .map(|result| {
+                    vec![
+                        result.port.to_string().cell(),
+                        result.status.to_string().cell(),
+                        if result.tls { "yes" } else { "no" }.cell(),
+                        result.server.clone().unwrap_or_default().cell(),
+                        result.powered_by.clone().unwrap_or_default().cell(),
+                        result.content_type.clone().unwrap_or_default().cell(),
+                        result.redirect.clone().unwrap_or_default().cell(),
+                    ]
+                })
+                .collect();
+            let table = rows.table().title(vec![
+                "Port".cell().bold(true),
+                "Status".cell().bold(true),
+                "TLS".cell().bold(true),
+                "Server".cell().bold(true),
+                "X-Powered-By".cell().bold(true),
+                "Content-Type".cell().bold(true),
+                "Redirect".cell().bold(true),
+            ]);
+            println!("{}", table.display().unwrap());
+        }
+        return;
+    }
+
+    let max_hosts = match arg_value(&args, "--max-hosts", "WEBSHOT_MAX_HOSTS") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(hosts) if hosts > 0 => hosts,
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid host limit (expected a positive number)".red());
+                return;
+            }
+        },
+        None => DEFAULT_MAX_HOSTS,
+    };
+
+    // CIDR blocks and comma separated lists are expanded here, a single target is resolved below
+    let target_hosts = match args[1].contains(',') || matches!(target_kind(&args[1]), TargetKind::Cidr(..)) {
+        true => match expand_targets(&args[1], args.contains(&"--ipv6".to_string()), max_hosts).await {
+            Ok(hosts) => Some(hosts),
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid targets".red(), e);
+                return;
+            }
+        },
+        false => None,
+    };
+
+    let top_ports = match arg_value(&args, "--top-ports", "WEBSHOT_TOP_PORTS") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(count) if (1..=TOP_PORTS.len()).contains(&count) => Some(count),
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), format!("Invalid top ports count (expected 1-{})", TOP_PORTS.len()).red());
+                return;
+            }
+        },
+        None => None,
+    };
+    let explicit_ports = args.len() > 2 && !args[2].starts_with("--");
+    if top_ports.is_some() && (explicit_ports || args.contains(&"--all".to_string())) {
+        eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "--top-ports cannot be combined with a port range or --all".red());
+        return;
+    }
+
+    let mut ports: Vec<u16> = Vec::new();
+    if explicit_ports {
+        ports = match parse_ports(&args[2]) {
+            Ok(ports) => ports,
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid Port Range".red(), e);
+                return;
+            }
+        };
+    } else if let Some(count) = top_ports {
+        ports = scanning::top_ports::top_ports(count);
+        status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Scanning the top {} ports", count).blue());
+    } else if let Some(profile_ports) = &profile.ports {
+        ports = profile_ports.clone();
+    }
+
+    if let Some(hosts) = &target_hosts {
+        ip = args[1].clone();
+        status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("{} expanded to {} hosts", ip, hosts.len()).blue());
     } else {
-        if let Some(ipv4) = ip_type.ipv4 {
-            ip = match ipv4 {
-                scanning::dns::IpType::V4(ip) => ip,
-                _ => String::new(),
-            };
+        let ip_type = match scanning::dns::resolve_domain(&args[1]).await {
+            Ok(addresses) => addresses,
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Could not resolve target".red(), e);
+                return;
+            }
+        };
+        if args.contains(&"--ipv6".to_string()) {
+            if let Some(ipv6) = ip_type.ipv6 {
+                ip = match ipv6 {
+                    scanning::dns::IpType::V6(ip) => ip,
+                    _ => String::new(),
+                };
+            }
+        } else {
+            if let Some(ipv4) = ip_type.ipv4 {
+                ip = match ipv4 {
+                    scanning::dns::IpType::V4(ip) => ip,
+                    _ => String::new(),
+                };
+            }
         }
     }
 
     if ports.is_empty() {
-        ports = vec![1, 443];
-        println!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), "No port specified. Scanning default ports 1-443".blue());
+        let set = if args.contains(&"--all".to_string()) {
+            "full".to_string()
+        } else {
+            arg_value(&args, "--default-ports", "WEBSHOT_DEFAULT_PORTS").unwrap_or(DEFAULT_PORT_SET.to_string())
+        };
+
+        ports = match port_set(&set) {
+            Some(ports) => ports,
+            None => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Unknown default port set (expected fast, common or full)".red(), set);
+                return;
+            }
+        };
+        status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("No port specified. Scanning the '{}' port set ({} ports)", set, ports.len()).blue());
     }
 
-    let user_agents = Arc::new(Mutex::new(get_user_agents().await));
+    let honeypot_threshold = match arg_value(&args, "--honeypot-threshold", "WEBSHOT_HONEYPOT_THRESHOLD") {
+        Some(value) => match value.parse::<f64>() {
+            Ok(threshold) if (0.0..=1.0).contains(&threshold) => threshold,
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid honeypot threshold (expected 0.0-1.0)".red());
+                return;
+            }
+        },
+        None => DEFAULT_HONEYPOT_THRESHOLD,
+    };
 
     let protocol = if args.contains(&"--udp".to_string()) {
         "UDP"
-    } else {
+    } else if args.contains(&"--tcp".to_string()) {
         "TCP"
+    } else {
+        profile.protocol.unwrap_or("TCP")
     };
 
-    let (tx, mut rx) = mpsc::channel(100);
+    let scan_type = if args.contains(&"--tcp-connect-only".to_string()) {
+        "connect".to_string()
+    } else {
+        arg_value(&args, "--scan-type", "WEBSHOT_SCAN_TYPE").unwrap_or("connect".to_string())
+    };
 
-    println!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), "Scanning... (This process may take time depending on connection speed)".blue());
-    for port in ports[0]..=ports[1] {
-        let tx = tx.clone();
-        let ip = ip.clone();
+    match scan_type.as_str() {
+        "connect" => {}
+        _ => {
+            eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Unknown scan type (expected connect)".red(), scan_type);
+            return;
+        }
+    }
 
-        tokio::spawn(async move {
-            match protocol {
-                "TCP" => {
-                    if let Some((open_port, banner, is_open)) = scan_tcp(&ip, port, Duration::from_secs(100)).await {
-                        tx.send((open_port, banner, is_open)).await.unwrap();
-                    }
-                }
-                "UDP" => {
-                    if let Some((open_port, banner, is_open)) = scan_udp(&ip, port, Duration::from_secs(100)).await {
-                        tx.send((open_port, banner, is_open)).await.unwrap();
-                    }
+    let min_banner_bytes = match arg_value(&args, "--min-banner-bytes", "WEBSHOT_MIN_BANNER_BYTES") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(bytes) => bytes,
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid minimum banner size (expected a number of bytes)".red());
+                return;
+            }
+        },
+        None => profile.min_banner_bytes.unwrap_or(1),
+    };
+
+    let chunk_size = match arg_value(&args, "--chunk-size", "WEBSHOT_CHUNK_SIZE") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(size) if size > 0 => Some(size),
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid chunk size (expected a positive number)".red());
+                return;
+            }
+        },
+        None => profile.chunk_size,
+    };
+
+    let retries = match arg_value(&args, "--retries", "WEBSHOT_RETRIES") {
+        Some(value) => match value.parse::<u32>() {
+            Ok(retries) => Some(retries),
+            Err(_) => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid retries (expected a number, 0 to disable)".red());
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let concurrency = match arg_value(&args, "--concurrency", "WEBSHOT_CONCURRENCY") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(concurrency) if concurrency > 0 => Some(concurrency),
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid concurrency (expected a positive number of ports)".red());
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let rate_limit = match arg_value(&args, "--rate-limit", "WEBSHOT_RATE_LIMIT") {
+        Some(value) => match value.parse::<u32>() {
+            Ok(rate) if rate > 0 => Some(rate),
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid rate limit (expected a positive number of packets per second)".red());
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let proxy = match arg_value(&args, "--http-proxy", "WEBSHOT_HTTP_PROXY") {
+        Some(value) => match Proxy::parse(&value) {
+            Ok(proxy) => Some(proxy),
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid proxy".red(), e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    if proxy.is_some() && protocol == "UDP" {
+        eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Proxies can only be used for TCP scans".red());
+        return;
+    }
+
+    let service_probes = match arg_value(&args, "--probes", "WEBSHOT_PROBES") {
+        Some(path) => match load_client_first(&path) {
+            Ok(probes) => Some(
+                probes
+                    .into_iter()
+                    .map(|(port, payload)| Box::new(PayloadProbe::new(port, payload)) as Box<dyn ServiceProbe>)
+                    .collect(),
+            ),
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid probes file".red(), e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    let banner_filter = match arg_value(&args, "--banner-regex", "WEBSHOT_BANNER_REGEX") {
+        Some(pattern) => match Regex::new(&pattern) {
+            Ok(regex) => Some(regex),
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Invalid banner regex".red(), e);
+                return;
+            }
+        },
+        None => None,
+    };
+
+    // A pattern that is not a valid regex is searched for as plain text
+    let banner_grep = arg_value(&args, "--banner-grep", "WEBSHOT_BANNER_GREP")
+        .map(|pattern| Regex::new(&pattern).unwrap_or_else(|_| Regex::new(&regex::escape(&pattern)).unwrap()));
+
+    if banner_filter.is_some() && banner_grep.is_some() {
+        eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "--banner-regex and --banner-grep cannot be combined".red());
+        return;
+    }
+
+    let repeat = match arg_value(&args, "--repeat", "WEBSHOT_REPEAT") {
+        Some(value) => match value.parse::<usize>() {
+            Ok(runs) if runs > 0 => runs,
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid repeat count (expected a positive number)".red());
+                return;
+            }
+        },
+        None => 1,
+    };
+
+    let watch = match arg_value(&args, "--watch", "WEBSHOT_WATCH") {
+        Some(value) => match value.parse::<u64>() {
+            Ok(seconds) if seconds > 0 => Some(Duration::from_secs(seconds)),
+            _ => {
+                eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "Invalid watch interval (expected a positive number of seconds)".red());
+                return;
+            }
+        },
+        None => None,
+    };
+
+    if args.contains(&"--csv".to_string()) && ["--xml", "--ports-csv", "--ports-csv-proto"].iter().any(|flag| args.contains(&flag.to_string())) {
+        eprintln!("[{}]{} {}", time, "[ERROR]".on_red(), "--csv cannot be combined with --xml or --ports-csv".red());
+        return;
+    }
+
+    // The extension of --output picks the format, the format flags decide otherwise
+    let output_format = match output.as_ref().and_then(|path| path.extension()).and_then(|extension| extension.to_str()) {
+        Some("xml") => "xml",
+        Some("csv") if args.contains(&"--ports-csv-proto".to_string()) => "csv-proto",
+        Some("csv") if args.contains(&"--ports-csv".to_string()) => "csv",
+        Some("csv") => "csv-table",
+        Some("json") => "json",
+        _ if args.contains(&"--ports-csv-proto".to_string()) => "csv-proto",
+        _ if args.contains(&"--ports-csv".to_string()) => "csv",
+        _ if args.contains(&"--xml".to_string()) => "xml",
+        _ if args.contains(&"--csv".to_string()) => "csv-table",
+        _ => "text",
+    };
+    let destination = match &output {
+        Some(path) => match Destination::create(path) {
+            Ok(destination) => destination,
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), format!("Cannot write to {}", path.display()).red(), e);
+                return;
+            }
+        },
+        None => Destination::Stdout,
+    };
+
+    let mut config = ScanConfig::new(ip, ports, protocol)
+        .with_timeout(timeout)
+        .with_min_banner_bytes(min_banner_bytes)
+        .with_retry_empty_banner(args.contains(&"--retry-empty-banner".to_string()))
+        .with_verbose_closed(args.contains(&"--verbose-closed".to_string()))
+        .with_half_open(args.contains(&"--half-open".to_string()))
+        .with_xml_output(output_format == "xml");
+    if let Some(chunk_size) = chunk_size {
+        config = config.with_chunk_size(chunk_size);
+    }
+    if let Some(concurrency) = concurrency {
+        config = config.with_concurrency(concurrency);
+    }
+    if let Some(rate_limit) = rate_limit {
+        config = config.with_rate_limit(rate_limit);
+    }
+    if let Some(retries) = retries {
+        config = config.with_retries(retries);
+    }
+    if let Some(path) = arg_value(&args, "--user-agents-file", "WEBSHOT_USER_AGENTS_FILE") {
+        config = config.with_user_agents_path(PathBuf::from(path));
+    }
+    // HTTP requests carry no User-Agent unless one is asked for
+    if args.contains(&"--random-agent".to_string()) {
+        let user_agents = get_user_agents(config.user_agents_path.as_deref()).await;
+        if !args.contains(&"--quiet".to_string()) {
+            let source = config.user_agents_path.as_ref().filter(|path| path.is_file()).map(|path| path.display().to_string()).unwrap_or_else(|| "the bundled list".to_string());
+            status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Loaded {} user agents from {}", user_agents.len(), source).blue());
+        }
+        if let Some(user_agent) = user_agents.choose(&mut rand::thread_rng()).cloned() {
+            config = config.with_random_agent(user_agent);
+        }
+    }
+    if let Some(proxy) = proxy {
+        config = config.with_proxy(proxy);
+    }
+    if let Some(hosts) = target_hosts {
+        config = config.with_hosts(hosts);
+    }
+
+    let mut resumed: Vec<ScanResult> = Vec::new();
+    if let Some(path) = arg_value(&args, "--progress-db", "WEBSHOT_PROGRESS_DB") {
+        let mut progress = match ProgressDb::open(&path) {
+            Ok(progress) => progress,
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Could not open progress database".red(), e);
+                return;
+            }
+        };
+        match progress.load(&config.hosts, protocol) {
+            Ok(results) => resumed = results,
+            Err(e) => {
+                eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Could not open progress database".red(), e);
+                return;
+            }
+        }
+        if progress.completed() > 0 {
+            status!(
+                "{}{} {}",
+                format!("[{}]", time).yellow(),
+                "[INFO]".blue(),
+                format!("Resuming from {}: {} ports already scanned, {} open", path, progress.completed(), resumed.len()).blue()
+            );
+        }
+        config = config.with_progress_db(progress);
+    }
+    if let Some(service_probes) = service_probes {
+        config = config.with_service_probes(service_probes);
+    }
+    if let Some(user) = arg_value(&args, "--finger-user", "WEBSHOT_FINGER_USER") {
+        config = config.with_service_probes(vec![Box::new(FingerUserProbe::new(user))]);
+    }
+    if let Some(banner_filter) = banner_filter {
+        config = config.with_banner_filter(banner_filter);
+    }
+    if let Some(banner_grep) = banner_grep {
+        config = config.with_banner_grep(banner_grep);
+    }
+
+    let scanner = NetworkScanner::new(config);
+
+    if args.contains(&"--try-axfr".to_string()) {
+        if let TargetKind::Domain(domain) = target_kind(&args[1]) {
+            status!("{}{} {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), "Attempting zone transfers against the authoritative nameservers (active check)".yellow());
+            match scanning::dns::try_zone_transfer(&domain, timeout).await {
+                Ok(outcomes) => {
+                    let allowed = outcomes.iter().filter(|(_, outcome)| matches!(outcome, AxfrResult::Transferred(_))).count();
+                    status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("{} of {} nameservers allowed a zone transfer", allowed, outcomes.len()).blue());
                 }
-                _ => {}
+                Err(e) => eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Zone transfer check failed".red(), e),
             }
-        });
+        } else {
+            status!("{}{} {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), "--try-axfr needs a domain target, skipping".yellow());
+        }
+    }
+
+    let connectivity = match args.contains(&"--test-connectivity".to_string()) {
+        true => {
+            let reachable = scanner.test_connectivity().await;
+            if reachable == Some(false) {
+                status!("{}{} {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), "Unable to reach target. Continuing scan, but results may be inaccurate.".yellow());
+            }
+            Some(reachable)
+        }
+        false => None,
+    };
+
+    let scan_started = Instant::now();
+    let mut stats = RepeatStats::default();
+    let mut errored_ports: BTreeSet<(String, u16)> = BTreeSet::new();
+    let mut results: Vec<ScanResult> = resumed;
+
+    status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Scan plan: {}", scanner.config()).blue());
+    status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), "Scanning... (This process may take time depending on connection speed)".blue());
+    for run in 1..=repeat {
+        if repeat > 1 {
+            status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Run {}/{}", run, repeat).blue());
+        }
+
+        let started = Instant::now();
+        // Open ports printed as they are found follow the status lines, to stderr when stdout is taken
+        let run = scanner.run().await;
+        stats.record(started.elapsed(), &run.results);
+        // Resumed ports are not scanned again, so only repeated runs can report a port twice
+        match repeat > 1 {
+            true => results = merge_results(results, run.results),
+            false => results.extend(run.results),
+        }
+        errored_ports.extend(run.errored_ports);
     }
 
-    drop(tx);
+    status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(),"Scan completed".green());
+    results.sort();
 
-    let mut results: Vec<(u16, String, String)> = Vec::new();
+    // A port that failed in one run but answered in another was scanned after all
+    errored_ports.retain(|(host, port)| !results.iter().any(|result| result.port == *port && result.target_ip.is_some_and(|ip| ip.to_string() == *host)));
+    if !errored_ports.is_empty() {
+        let ports = errored_ports.iter().map(|(host, port)| endpoint_label(scanner.config(), host, *port)).collect::<Vec<_>>().join(",");
+        status!("{}{} {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), format!("Scan is incomplete, {} ports could not be scanned: {}", errored_ports.len(), ports).yellow());
+    }
+
+    if stats.runs() > 1 {
+        status!(
+            "{}{} {}",
+            format!("[{}]", time).yellow(),
+            "[INFO]".blue(),
+            format!(
+                "{} runs => mean: {:.2?}, min: {:.2?}, max: {:.2?}",
+                stats.runs(),
+                stats.mean(),
+                stats.min(),
+                stats.max()
+            ).blue()
+        );
 
-    while let Some((open_port, banner, service)) = rx.recv().await {
-        results.push((open_port, banner, service));
+        for run in stats.inconsistent_runs() {
+            status!("{}{} {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), format!("Run {} found a different set of open ports than the other runs", run).yellow());
+        }
+    }
+
+    // The analyses look at one host at a time; results are sorted, so each host's ports are contiguous
+    let multi_host = scanner.config().hosts.len() > 1;
+    for host_results in results.chunk_by(|a, b| a.target_ip == b.target_ip) {
+        let host = match multi_host {
+            true => host_results[0].target_ip.map(|ip| format!("{}: ", ip)).unwrap_or_default(),
+            false => String::new(),
+        };
+
+        if likely_domain_controller(host_results) {
+            status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("{}Kerberos is open alongside LDAP/SMB, target is likely an Active Directory Domain Controller", host).green());
+        }
+
+        let scanned_ports = scanner.config().ports.len();
+        if looks_like_honeypot(host_results, scanned_ports, honeypot_threshold) {
+            status!(
+                "{}{} {}",
+                format!("[{}]", time).yellow(),
+                "[WARN]".bright_yellow(),
+                format!("{}{} of {} ports answered with near-identical banners, target may be a honeypot/tarpit", host, host_results.len(), scanned_ports).yellow()
+            );
+        }
+
+        for (plugin, finding) in PluginRegistry::with_builtin().run(host_results) {
+            let port = finding.port.map(|port| port.to_string()).unwrap_or_default();
+            status!("{}{} {}{} => {}", finding.severity.label(), format!("[{}]", plugin).yellow(), host.yellow(), port.yellow(), finding.message);
+        }
     }
 
-    println!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(),"Scan completed".green());
+    let mut writers: Vec<Box<dyn OutputWriter>> = vec![match output_format {
+        _ if scanner.config().xml_output => Box::new(XmlWriter::new(destination)),
+        "csv-proto" => Box::new(PortsCsvWriter::new(true, destination)),
+        "csv" => Box::new(PortsCsvWriter::new(false, destination)),
+        "json" => Box::new(JsonWriter::new(destination)),
+        "csv-table" => Box::new(CsvWriter::new(destination, match target_kind(&args[1]) {
+            TargetKind::Domain(name) => Some(name),
+            _ => None,
+        })),
+        _ => Box::new(TextWriter::new(destination)),
+    }];
+    if let Some(path) = arg_value(&args, "--sqlite", "WEBSHOT_SQLITE") {
+        writers.push(Box::new(SqliteWriter::new(&path)));
+    }
+
+    let mut summary = ScanSummary::complete(scanner.config(), &results, errored_ports.len(), scan_started.elapsed());
+    if let Some(reachable) = connectivity {
+        summary = summary.with_connectivity(reachable);
+    }
+    for writer in &mut writers {
+        for result in &results {
+            writer.write_result(result);
+        }
+        writer.write_summary(&summary);
+    }
+
+    if let Some(url) = arg_value(&args, "--push-gateway", "WEBSHOT_PUSH_GATEWAY") {
+        let scan_id = Local::now().format("%Y%m%d%H%M%S").to_string();
+        match push_to_gateway(&url, &summary, &scan_id).await {
+            Ok(()) => status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Pushed scan metrics to {} (scan_id {})", url, scan_id).blue()),
+            Err(e) => eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), "Could not push metrics".red(), e),
+        }
+    }
+
+    if let Some(interval) = watch {
+        watch_for_changes(&scanner, results, interval, arg_value(&args, "--sqlite", "WEBSHOT_SQLITE")).await;
+    }
+}
+
+/// Rescans every `interval` and prints only what changed since the previous scan, until Ctrl+C.
+/// Each rescan is also stored when a SQLite database is given.
+async fn watch_for_changes(scanner: &NetworkScanner, mut previous: Vec<ScanResult>, interval: Duration, sqlite: Option<String>) {
+    let time = Local::now().format("%H:%M:%S").to_string();
+    let multi_host = scanner.config().hosts.len() > 1;
+    status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), format!("Watching for changes every {:?}, press Ctrl+C to stop", interval).blue());
+
+    loop {
+        let started = Instant::now();
+        let run = tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            run = async {
+                tokio::time::sleep(interval).await;
+                scanner.run_silent().await
+            } => run,
+        };
+
+        let time = Local::now().format("%H:%M:%S").to_string();
+        let changes = diff_results(&previous, &run.results);
+        if changes.is_empty() {
+            status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), "No changes".blue());
+        }
+        for change in &changes {
+            match change {
+                ResultChange::Opened(result) if result.banner.trim().is_empty() => {
+                    status!("{}{} {} => {}", "[NEW OPEN]".green(), format!("[{}]", result.protocol).yellow(), result.endpoint(multi_host).yellow(), "No Meaningful Banner".yellow());
+                }
+                ResultChange::Opened(result) => {
+                    status!("{}{} {} => {}: {}", "[NEW OPEN]".green(), format!("[{}]", result.protocol).yellow(), result.endpoint(multi_host).yellow(), "Response".green(), result.banner.trim());
+                }
+                ResultChange::Closed(result) => {
+                    status!("{}{} {}", "[NEW CLOSED]".red(), format!("[{}]", result.protocol).yellow(), result.endpoint(multi_host).yellow());
+                }
+                ResultChange::BannerChanged { before, after } => {
+                    status!("{}{} {} => {} -> {}", "[CHANGED]".bright_yellow(), format!("[{}]", after.protocol).yellow(), after.endpoint(multi_host).yellow(), before.banner.trim(), after.banner.trim());
+                }
+            }
+        }
+
+        if let Some(path) = &sqlite {
+            let mut writer = SqliteWriter::new(path);
+            for result in &run.results {
+                writer.write_result(result);
+            }
+            writer.write_summary(&ScanSummary::complete(scanner.config(), &run.results, run.errored_ports.len(), started.elapsed()));
+        }
+
+        previous = run.results;
+    }
+
+    let time = Local::now().format("%H:%M:%S").to_string();
+    status!("{}{} {}", format!("[{}]", time).yellow(), "[INFO]".blue(), "Stopped watching".blue());
+}
+
+// An explicit flag always wins over the environment variable
+fn arg_value(args: &[String], flag: &str, env_var: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .cloned()
+        .or_else(|| env::var(env_var).ok())
 }
\ No newline at end of file
@@ -0,0 +1,163 @@
+use std::collections::{BTreeSet, HashMap};
+use std::time::Duration;
+use crate::scanning::types::ScanResult;
+
+pub const DEFAULT_HONEYPOT_THRESHOLD: f64 = 0.9;
+
+// Below this many scanned ports the open ratio says nothing useful
+const MIN_PORTS_FOR_HONEYPOT_CHECK: usize = 10;
+
+fn normalize_banner(banner: &str) -> String {
+    banner
+        .lines()
+        .next()
+        .unwrap_or("")
+        .chars()
+        .filter(|c| !c.is_ascii_digit())
+        .collect::<String>()
+        .trim()
+        .to_lowercase()
+}
+
+/// Flags a host where an implausibly high share of the scanned ports is open
+/// and answers with (nearly) the same banner, which is typical for honeypots and tarpits.
+pub fn looks_like_honeypot(results: &[ScanResult], scanned_ports: usize, threshold: f64) -> bool {
+    if scanned_ports < MIN_PORTS_FOR_HONEYPOT_CHECK || results.is_empty() {
+        return false;
+    }
+
+    let open_ratio = results.len() as f64 / scanned_ports as f64;
+    if open_ratio < threshold {
+        return false;
+    }
+
+    let mut banners: HashMap<String, usize> = HashMap::new();
+    for result in results {
+        *banners.entry(normalize_banner(&result.banner)).or_insert(0) += 1;
+    }
+
+    let most_common = banners.values().copied().max().unwrap_or(0);
+    most_common as f64 / results.len() as f64 >= threshold
+}
+
+/// Kerberos next to LDAP or SMB on the same host is the usual footprint of an
+/// Active Directory Domain Controller.
+pub fn likely_domain_controller(results: &[ScanResult]) -> bool {
+    let is_open = |port: u16| results.iter().any(|result| result.port == port);
+    is_open(88) && (is_open(389) || is_open(445))
+}
+
+/// Timing and result consistency across repeated runs of the same scan.
+#[derive(Default)]
+pub struct RepeatStats {
+    durations: Vec<Duration>,
+    open_ports: Vec<BTreeSet<u16>>,
+}
+
+impl RepeatStats {
+    pub fn record(&mut self, duration: Duration, results: &[ScanResult]) {
+        self.durations.push(duration);
+        self.open_ports.push(results.iter().map(|result| result.port).collect());
+    }
+
+    pub fn runs(&self) -> usize {
+        self.durations.len()
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.durations.is_empty() {
+            return Duration::ZERO;
+        }
+        self.durations.iter().sum::<Duration>() / self.durations.len() as u32
+    }
+
+    pub fn min(&self) -> Duration {
+        self.durations.iter().min().copied().unwrap_or_default()
+    }
+
+    pub fn max(&self) -> Duration {
+        self.durations.iter().max().copied().unwrap_or_default()
+    }
+
+    /// 1-based indexes of runs whose open ports differ from the most common result.
+    pub fn inconsistent_runs(&self) -> Vec<usize> {
+        let mut counts: HashMap<&BTreeSet<u16>, usize> = HashMap::new();
+        for ports in &self.open_ports {
+            *counts.entry(ports).or_insert(0) += 1;
+        }
+
+        let Some((expected, _)) = counts.into_iter().max_by_key(|(_, count)| *count) else {
+            return Vec::new();
+        };
+
+        self.open_ports
+            .iter()
+            .enumerate()
+            .filter(|(_, ports)| *ports != expected)
+            .map(|(index, _)| index + 1)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_ports(count: u16, banner: impl Fn(u16) -> String) -> Vec<ScanResult> {
+        (1..=count).map(|port| ScanResult::new(port, "TCP", banner(port), "Unknown".to_string())).collect()
+    }
+
+    #[test]
+    fn every_port_open_with_the_same_banner_is_a_honeypot() {
+        let results = open_ports(20, |_| "220 ProFTPD Server ready\r\n".to_string());
+        assert!(looks_like_honeypot(&results, 20, DEFAULT_HONEYPOT_THRESHOLD));
+    }
+
+    #[test]
+    fn banners_differing_only_in_digits_count_as_the_same() {
+        let results = open_ports(20, |port| format!("SSH-2.0-OpenSSH_{}.{}\r\nsession {}", port, port * 7, port));
+        assert!(looks_like_honeypot(&results, 20, DEFAULT_HONEYPOT_THRESHOLD));
+    }
+
+    #[test]
+    fn distinct_services_are_not_a_honeypot() {
+        let results = open_ports(20, |port| ["SSH-2.0-OpenSSH", "220 mail ESMTP", "+OK POP3", "* OK IMAP"][port as usize % 4].to_string());
+        assert!(!looks_like_honeypot(&results, 20, DEFAULT_HONEYPOT_THRESHOLD));
+    }
+
+    #[test]
+    fn small_scans_and_empty_results_are_never_flagged() {
+        let results = open_ports(5, |_| "same".to_string());
+        assert!(!looks_like_honeypot(&results, 5, DEFAULT_HONEYPOT_THRESHOLD));
+        assert!(!looks_like_honeypot(&[], 100, DEFAULT_HONEYPOT_THRESHOLD));
+    }
+
+    // 20 ports scanned at the default 0.9: 18 open is exactly on the threshold
+    #[test]
+    fn open_share_just_below_the_threshold_is_not_flagged() {
+        let results = open_ports(17, |_| "same banner".to_string());
+        assert!(!looks_like_honeypot(&results, 20, DEFAULT_HONEYPOT_THRESHOLD));
+    }
+
+    #[test]
+    fn open_share_at_and_just_above_the_threshold_is_flagged() {
+        assert!(looks_like_honeypot(&open_ports(18, |_| "same banner".to_string()), 20, DEFAULT_HONEYPOT_THRESHOLD));
+        assert!(looks_like_honeypot(&open_ports(19, |_| "same banner".to_string()), 20, DEFAULT_HONEYPOT_THRESHOLD));
+    }
+
+    #[test]
+    fn banner_similarity_just_below_the_threshold_is_not_flagged() {
+        // 17 of 19 banners match: 0.89, under the 0.9 threshold
+        let results = open_ports(19, |port| if port <= 17 { "same banner".to_string() } else { format!("other {}", char::from(b'a' + port as u8)) });
+        assert!(!looks_like_honeypot(&results, 20, DEFAULT_HONEYPOT_THRESHOLD));
+    }
+
+    #[test]
+    fn kerberos_with_ldap_or_smb_is_a_domain_controller() {
+        let ports = |ports: &[u16]| ports.iter().map(|&port| ScanResult::new(port, "TCP", String::new(), "Unknown".to_string())).collect::<Vec<_>>();
+        assert!(likely_domain_controller(&ports(&[53, 88, 389])));
+        assert!(likely_domain_controller(&ports(&[88, 445])));
+        assert!(!likely_domain_controller(&ports(&[88, 80])));
+        assert!(!likely_domain_controller(&ports(&[389, 445])));
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicI64, AtomicUsize, Ordering};
+use std::sync::{mpsc, Mutex};
+use std::thread::{self, JoinHandle};
+use colored::Colorize;
+use rusqlite::{params, Connection};
+use tokio::sync::oneshot;
+use crate::scanning::types::ScanResult;
+
+const PROGRESS_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS progress (
+        target TEXT NOT NULL,
+        protocol TEXT NOT NULL,
+        port INTEGER NOT NULL,
+        run INTEGER NOT NULL DEFAULT 0,
+        open INTEGER NOT NULL,
+        banner TEXT,
+        service TEXT,
+        PRIMARY KEY (target, protocol, port)
+    );
+";
+
+/// Most ports written in one transaction.
+const MAX_BATCH: usize = 500;
+
+struct Record {
+    target: String,
+    protocol: &'static str,
+    port: u16,
+    run: i64,
+    result: Option<(String, String)>,
+}
+
+enum Message {
+    Record(Record),
+    /// Answered once everything sent before it is written
+    Flush(oneshot::Sender<()>),
+}
+
+/// Records every finished port of a long scan in a SQLite database, so an interrupted scan
+/// can be resumed. The database runs in WAL mode and can be queried while the scan runs.
+///
+/// Ports are written by a single thread, in batches, so scan tasks never wait on the database.
+/// Every row carries the run that scanned it; only the ports of the latest run are skipped.
+pub struct ProgressDb {
+    path: String,
+    writer: Option<(mpsc::Sender<Message>, JoinHandle<()>)>,
+    run: AtomicI64,
+    /// How many times `begin_run` was called
+    rounds: AtomicUsize,
+    /// Host and port of everything `load` found, which the scanner skips in the first round
+    completed: Mutex<HashSet<(String, u16)>>,
+}
+
+impl ProgressDb {
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.pragma_update(None, "journal_mode", "WAL")?;
+        connection.execute_batch(PROGRESS_SCHEMA)?;
+
+        let (tx, rx) = mpsc::channel();
+        let writer = thread::spawn(move || write_batches(connection, rx));
+        Ok(ProgressDb {
+            path: path.to_string(),
+            writer: Some((tx, writer)),
+            run: AtomicI64::new(0),
+            rounds: AtomicUsize::new(0),
+            completed: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Loads what the latest run recorded for the hosts and returns the open ports among them.
+    /// The other recorded ports are remembered as completed too, and new ones continue that run.
+    pub fn load(&mut self, hosts: &[String], protocol: &'static str) -> rusqlite::Result<Vec<ScanResult>> {
+        let connection = Connection::open(&self.path)?;
+        let mut latest = connection.prepare("SELECT COALESCE(MAX(run), 0) FROM progress WHERE target = ?1 AND protocol = ?2")?;
+        let mut run = 0;
+        for host in hosts {
+            run = run.max(latest.query_row(params![host, protocol], |row| row.get::<_, i64>(0))?);
+        }
+        *self.run.get_mut() = run;
+
+        let mut statement = connection.prepare("SELECT port, open, banner, service FROM progress WHERE target = ?1 AND protocol = ?2 AND run = ?3")?;
+        let completed = self.completed.get_mut().unwrap();
+        let mut results = Vec::new();
+        for host in hosts {
+            let rows = statement.query_map(params![host, protocol, run], |row| {
+                Ok((
+                    row.get::<_, u16>(0)?,
+                    row.get::<_, bool>(1)?,
+                    row.get::<_, Option<String>>(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            })?;
+
+            for row in rows {
+                let (port, open, banner, service) = row?;
+                completed.insert((host.clone(), port));
+                if open {
+                    let result = ScanResult::new(port, protocol, banner.unwrap_or_default(), service.unwrap_or_else(|| "Unknown".to_string()));
+                    results.push(result.with_target_ip(host.parse().ok()));
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    pub fn completed(&self) -> usize {
+        self.completed.lock().unwrap().len()
+    }
+
+    pub fn is_completed(&self, host: &str, port: u16) -> bool {
+        self.completed.lock().unwrap().contains(&(host.to_string(), port))
+    }
+
+    /// Called as each scan of the ports starts. The first one resumes the run `load` found; the
+    /// ones after it, with `--watch` or `--repeat`, start a new run that scans every port again.
+    pub fn begin_run(&self) {
+        if self.rounds.fetch_add(1, Ordering::SeqCst) > 0 {
+            self.run.fetch_add(1, Ordering::SeqCst);
+            self.completed.lock().unwrap().clear();
+        }
+    }
+
+    /// Queues the port to be marked as done, with its result when it was reported as open.
+    pub fn record(&self, target: &str, protocol: &'static str, port: u16, result: Option<&ScanResult>) {
+        let record = Record {
+            target: target.to_string(),
+            protocol,
+            port,
+            run: self.run.load(Ordering::SeqCst),
+            result: result.map(|result| (result.banner.clone(), result.service.clone())),
+        };
+        if let Some((tx, _)) = &self.writer {
+            tx.send(Message::Record(record)).ok();
+        }
+    }
+
+    /// Waits until every port recorded so far is written.
+    pub async fn flush(&self) {
+        let (done, written) = oneshot::channel();
+        if let Some((tx, _)) = &self.writer {
+            if tx.send(Message::Flush(done)).is_ok() {
+                written.await.ok();
+            }
+        }
+    }
+}
+
+impl Drop for ProgressDb {
+    fn drop(&mut self) {
+        // Closing the channel lets the writer finish what is queued and stop
+        if let Some((tx, writer)) = self.writer.take() {
+            drop(tx);
+            writer.join().ok();
+        }
+    }
+}
+
+/// Writes records as they arrive, everything queued at the time in one transaction.
+fn write_batches(mut connection: Connection, messages: mpsc::Receiver<Message>) {
+    while let Ok(first) = messages.recv() {
+        let mut batch = Vec::new();
+        let mut flushes = Vec::new();
+        for message in std::iter::once(first).chain(messages.try_iter()) {
+            match message {
+                Message::Record(record) => batch.push(record),
+                Message::Flush(done) => flushes.push(done),
+            }
+            if batch.len() == MAX_BATCH {
+                break;
+            }
+        }
+
+        if let Err(e) = write_batch(&mut connection, &batch) {
+            eprintln!("{} {}: {}", "[ERROR]".red(), "Could not record progress".red(), e);
+        }
+        for done in flushes {
+            done.send(()).ok();
+        }
+    }
+}
+
+fn write_batch(connection: &mut Connection, batch: &[Record]) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    {
+        let mut statement = transaction.prepare_cached(
+            "INSERT OR REPLACE INTO progress (target, protocol, port, run, open, banner, service) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for record in batch {
+            let result = record.result.as_ref();
+            statement.execute(params![
+                record.target,
+                record.protocol,
+                record.port,
+                record.run,
+                result.is_some(),
+                result.map(|(banner, _)| banner),
+                result.map(|(_, service)| service),
+            ])?;
+        }
+    }
+    transaction.commit()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+    use crate::scanning::scanner::{NetworkScanner, ScanConfig};
+    use crate::scanning::testing::{closed_port, tcp_banner_server};
+
+    fn open_result(port: u16) -> ScanResult {
+        ScanResult::new(port, "TCP", "220 ready\r\n".to_string(), "FTP".to_string())
+    }
+
+    #[tokio::test]
+    async fn recorded_ports_are_resumed_from_the_latest_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("progress.db");
+        let path = path.to_str().unwrap();
+        let hosts = ["127.0.0.1".to_string()];
+
+        let progress = ProgressDb::open(path).unwrap();
+        progress.begin_run();
+        progress.record("127.0.0.1", "TCP", 21, Some(&open_result(21)));
+        progress.record("127.0.0.1", "TCP", 22, None);
+        // A second round, as with --watch, that was interrupted after one port
+        progress.begin_run();
+        progress.record("127.0.0.1", "TCP", 21, Some(&open_result(21)));
+        progress.flush().await;
+        drop(progress);
+
+        let mut progress = ProgressDb::open(path).unwrap();
+        let resumed = progress.load(&hosts, "TCP").unwrap();
+        assert_eq!(resumed.iter().map(|result| result.port).collect::<Vec<_>>(), vec![21]);
+        assert!(progress.is_completed("127.0.0.1", 21));
+        assert!(!progress.is_completed("127.0.0.1", 22));
+        assert!(progress.load(&hosts, "UDP").unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn later_rounds_scan_every_port_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("progress.db");
+        let ports = vec![tcp_banner_server(b"220 ready\r\n").await, closed_port().await];
+        let hosts = ["127.0.0.1".to_string()];
+
+        let progress = ProgressDb::open(path.to_str().unwrap()).unwrap();
+        let config = ScanConfig::new("127.0.0.1".to_string(), ports.clone(), "TCP").with_timeout(Duration::from_secs(2));
+        NetworkScanner::new(config.with_progress_db(progress)).run_silent().await;
+
+        // Resuming a finished scan skips every port once, then rescans them
+        let mut progress = ProgressDb::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(progress.load(&hosts, "TCP").unwrap().len(), 1);
+        assert_eq!(progress.completed(), 2);
+        let config = ScanConfig::new("127.0.0.1".to_string(), ports.clone(), "TCP").with_timeout(Duration::from_secs(2));
+        let scanner = NetworkScanner::new(config.with_progress_db(progress));
+
+        assert!(scanner.run_silent().await.results.is_empty());
+        assert_eq!(scanner.run_silent().await.results.iter().map(|result| result.port).collect::<Vec<_>>(), vec![ports[0]]);
+        assert_eq!(scanner.config().progress.as_ref().unwrap().completed(), 0);
+    }
+}
@@ -1,39 +1,159 @@
-use std::net::ToSocketAddrs;
-use std::process::exit;
+use std::collections::HashSet;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
 use colored::Colorize;
 use url::Url;
 use chrono::Local;
-use tokio::net::lookup_host;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{lookup_host, TcpStream, UdpSocket};
+use crate::scanning::protocols::Severity;
 
 #[derive(Debug)]
-pub(crate) enum IpType {
+pub enum IpType {
     V4(String),
     V6(String),
 }
 
 pub struct IpAddresses {
-    pub(crate) ipv4: Option<IpType>,
-    pub(crate) ipv6: Option<IpType>,
+    pub ipv4: Option<IpType>,
+    pub ipv6: Option<IpType>,
 }
 
-pub async fn resolve_domain(domain: &str) -> IpAddresses {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TargetKind {
+    Ip(IpAddr),
+    Domain(String),
+    Cidr(IpAddr, u8),
+    Invalid,
+}
+
+fn is_valid_domain(name: &str) -> bool {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    !name.is_empty()
+        && name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        })
+        // All-numeric names are malformed IPs, not domains
+        && !name.split('.').all(|label| label.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Classifies a command line target: a bare or bracketed IP, an IP/prefix CIDR block,
+/// a domain, or a URL whose host is one of those.
+pub fn target_kind(target: &str) -> TargetKind {
+    let target = target.trim();
+
+    if target.contains("://") {
+        return match Url::parse(target).ok().and_then(|url| url.host_str().map(String::from)) {
+            Some(host) => target_kind(&host),
+            None => TargetKind::Invalid,
+        };
+    }
+
+    if let Some((ip, prefix)) = target.split_once('/') {
+        return match (ip.parse::<IpAddr>(), prefix.parse::<u8>()) {
+            (Ok(ip), Ok(prefix)) if prefix <= if ip.is_ipv4() { 32 } else { 128 } => TargetKind::Cidr(ip, prefix),
+            _ => TargetKind::Invalid,
+        };
+    }
+
+    let unbracketed = target.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(ip) = unbracketed.parse::<IpAddr>() {
+        return TargetKind::Ip(ip);
+    }
+
+    if is_valid_domain(target) {
+        TargetKind::Domain(target.to_lowercase())
+    } else {
+        TargetKind::Invalid
+    }
+}
+
+/// Most hosts one scan expands its targets to unless `--max-hosts` says otherwise, an IPv4 /16
+pub const DEFAULT_MAX_HOSTS: usize = 65536;
+
+/// Expands an IP or CIDR target into the addresses to scan. IPv4 blocks larger than a /31
+/// leave out the network and broadcast addresses. Domains are resolved by `resolve_domain` instead.
+pub fn parse_target(target: &str, max_hosts: usize) -> Result<Vec<IpAddr>, String> {
+    match target_kind(target) {
+        TargetKind::Ip(ip) => Ok(vec![ip]),
+        TargetKind::Cidr(IpAddr::V4(ip), prefix) => {
+            let size = 1u128 << (32 - prefix);
+            if size > max_hosts as u128 {
+                return Err(format!("{} has {} addresses, the limit is {}", target, size, max_hosts));
+            }
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            let network = u32::from(ip) & mask;
+            let (first, last) = match prefix {
+                31 | 32 => (network, network | !mask),
+                _ => (network + 1, (network | !mask) - 1),
+            };
+            Ok((first..=last).map(|host| IpAddr::V4(Ipv4Addr::from(host))).collect())
+        }
+        TargetKind::Cidr(IpAddr::V6(ip), prefix) => {
+            let size = 1u128.checked_shl(128 - prefix as u32).unwrap_or(u128::MAX);
+            if size > max_hosts as u128 {
+                return Err(format!("{} is larger than {} addresses", target, max_hosts));
+            }
+            let network = u128::from(ip) & !(size - 1);
+            Ok((0..size).map(|offset| IpAddr::V6(Ipv6Addr::from(network + offset))).collect())
+        }
+        TargetKind::Domain(_) | TargetKind::Invalid => Err(format!("{} is not an IP address or CIDR block", target)),
+    }
+}
+
+/// Expands a comma separated list of IPs, CIDR blocks and domains into the hosts to scan,
+/// without duplicates and in the order given. Domains resolve to their IPv4 address, or to
+/// the IPv6 one with `ipv6`.
+pub async fn expand_targets(targets: &str, ipv6: bool, max_hosts: usize) -> Result<Vec<IpAddr>, String> {
+    let mut seen = HashSet::new();
+    let mut hosts = Vec::new();
+
+    for target in targets.split(',').map(str::trim).filter(|target| !target.is_empty()) {
+        let expanded = match target_kind(target) {
+            TargetKind::Domain(_) => {
+                let addresses = resolve_domain(target).await?;
+                let ip = match if ipv6 { addresses.ipv6 } else { addresses.ipv4 } {
+                    Some(IpType::V4(ip)) | Some(IpType::V6(ip)) => ip,
+                    None => return Err(format!("{} has no {} address", target, if ipv6 { "IPv6" } else { "IPv4" })),
+                };
+                vec![ip.parse::<IpAddr>().map_err(|e| e.to_string())?]
+            }
+            _ => parse_target(target, max_hosts)?,
+        };
+
+        hosts.extend(expanded.into_iter().filter(|host| seen.insert(*host)));
+        if hosts.len() > max_hosts {
+            return Err(format!("targets expand to more than {} hosts", max_hosts));
+        }
+    }
+
+    if hosts.is_empty() {
+        return Err("no targets given".to_string());
+    }
+    Ok(hosts)
+}
+
+/// Looks up the IPv4 and IPv6 address of a domain; an IP target is returned as is.
+pub async fn resolve_domain(domain: &str) -> Result<IpAddresses, String> {
     let time = Local::now().format("%H:%M:%S").to_string();
 
-    let host_str = Url::parse(domain).ok().and_then(|url| url.host_str().map(String::from));
+    let host = match target_kind(domain) {
+        TargetKind::Ip(ip) => ip.to_string(),
+        TargetKind::Domain(name) => name,
+        TargetKind::Cidr(..) => return Err(format!("{} is a CIDR block, not a single host", domain)),
+        TargetKind::Invalid => return Err(format!("{} is not an IP address or domain", domain)),
+    };
 
-    let addr_iter = match lookup_host((host_str.unwrap().as_str(), 0)).await {
+    let addr_iter = match lookup_host((host.as_str(), 0)).await {
         Ok(addrs) => addrs.collect::<Vec<_>>(),
-        Err(e) => {
-            eprintln!(
-                "{}{} {}: {} ({})",
-                format!("[{}]", time),
-                "[ERROR]".on_red(),
-                "Failed to resolve domain".red(),
-                domain,
-                e
-            );
-            exit(1);
-        }
+        Err(e) => return Err(format!("failed to resolve {} ({})", domain, e)),
     };
 
     let mut ipv4 = None;
@@ -47,7 +167,7 @@ pub async fn resolve_domain(domain: &str) -> IpAddresses {
     }
 
     if let Some(ip) = &ipv4 {
-        println!(
+        crate::status!(
             "{}{} {}: {:?}",
             format!("[{}]", time).yellow(),
             "[INFO]".blue(),
@@ -57,7 +177,7 @@ pub async fn resolve_domain(domain: &str) -> IpAddresses {
     }
 
     if let Some(ip) = &ipv6 {
-        println!(
+        crate::status!(
             "{}{} {}: {:?}",
             format!("[{}]", time).yellow(),
             "[INFO]".blue(),
@@ -66,5 +186,463 @@ pub async fn resolve_domain(domain: &str) -> IpAddresses {
         );
     }
 
-    IpAddresses { ipv4, ipv6 }
-}
\ No newline at end of file
+    Ok(IpAddresses { ipv4, ipv6 })
+}
+
+pub(crate) const TYPE_A: u16 = 1;
+const TYPE_NS: u16 = 2;
+const TYPE_CNAME: u16 = 5;
+const TYPE_SOA: u16 = 6;
+pub(crate) const TYPE_PTR: u16 = 12;
+const TYPE_MX: u16 = 15;
+pub(crate) const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+pub(crate) const TYPE_SRV: u16 = 33;
+const TYPE_AXFR: u16 = 252;
+
+const CLASS_IN: u16 = 1;
+
+#[derive(Debug, Clone)]
+pub enum RecordData {
+    A(Ipv4Addr),
+    Aaaa(Ipv6Addr),
+    Ns(String),
+    Cname(String),
+    Soa { mname: String, serial: u32 },
+    Mx { preference: u16, exchange: String },
+    Ptr(String),
+    Srv { port: u16, target: String },
+    Txt(Vec<String>),
+    Other(u16),
+}
+
+impl fmt::Display for RecordData {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordData::A(ip) => write!(f, "A {}", ip),
+            RecordData::Aaaa(ip) => write!(f, "AAAA {}", ip),
+            RecordData::Ns(name) => write!(f, "NS {}", name),
+            RecordData::Cname(name) => write!(f, "CNAME {}", name),
+            RecordData::Soa { mname, serial } => write!(f, "SOA {} (serial {})", mname, serial),
+            RecordData::Mx { preference, exchange } => write!(f, "MX {} {}", preference, exchange),
+            RecordData::Ptr(name) => write!(f, "PTR {}", name),
+            RecordData::Srv { port, target } => write!(f, "SRV {}:{}", target, port),
+            RecordData::Txt(strings) => write!(f, "TXT \"{}\"", strings.join("\" \"")),
+            RecordData::Other(rtype) => write!(f, "TYPE{}", rtype),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DnsRecord {
+    pub name: String,
+    pub data: RecordData,
+}
+
+#[derive(Debug)]
+pub(crate) struct DnsMessage {
+    pub(crate) id: u16,
+    pub(crate) recursion_available: bool,
+    pub(crate) rcode: u8,
+    /// Answer, authority and additional sections in wire order
+    pub(crate) records: Vec<DnsRecord>,
+}
+
+pub(crate) fn build_query(id: u16, name: &str, qtype: u16, recursion_desired: bool) -> Vec<u8> {
+    let flags: u16 = if recursion_desired { 0x0100 } else { 0 };
+    let mut packet = Vec::with_capacity(name.len() + 18);
+    packet.extend_from_slice(&id.to_be_bytes());
+    packet.extend_from_slice(&flags.to_be_bytes());
+    packet.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+
+    for label in name.trim_end_matches('.').split('.').filter(|l| !l.is_empty()) {
+        packet.push(label.len().min(63) as u8);
+        packet.extend_from_slice(&label.as_bytes()[..label.len().min(63)]);
+    }
+    packet.push(0);
+    packet.extend_from_slice(&qtype.to_be_bytes());
+    packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+    packet
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*buf.get(pos)?, *buf.get(pos + 1)?]))
+}
+
+/// Reads a possibly compressed name and returns it together with the offset right after it.
+fn read_name(buf: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *buf.get(pos)? as usize;
+        if len == 0 {
+            pos += 1;
+            break;
+        }
+
+        if len & 0xC0 == 0xC0 {
+            jumps += 1;
+            if jumps > 16 {
+                return None;
+            }
+            let pointer = (read_u16(buf, pos)? & 0x3FFF) as usize;
+            end.get_or_insert(pos + 2);
+            pos = pointer;
+            continue;
+        }
+
+        let label = buf.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_string());
+        pos += 1 + len;
+    }
+
+    Some((labels.join("."), end.unwrap_or(pos)))
+}
+
+fn parse_rdata(buf: &[u8], rtype: u16, start: usize, len: usize) -> Option<RecordData> {
+    let rdata = buf.get(start..start + len)?;
+    let data = match rtype {
+        TYPE_A => RecordData::A(Ipv4Addr::from(<[u8; 4]>::try_from(rdata).ok()?)),
+        TYPE_AAAA => RecordData::Aaaa(Ipv6Addr::from(<[u8; 16]>::try_from(rdata).ok()?)),
+        TYPE_NS => RecordData::Ns(read_name(buf, start)?.0),
+        TYPE_CNAME => RecordData::Cname(read_name(buf, start)?.0),
+        TYPE_SOA => {
+            let (mname, next) = read_name(buf, start)?;
+            let (_, next) = read_name(buf, next)?;
+            let serial = buf.get(next..next + 4)?;
+            RecordData::Soa { mname, serial: u32::from_be_bytes(serial.try_into().ok()?) }
+        }
+        TYPE_MX => RecordData::Mx {
+            preference: read_u16(buf, start)?,
+            exchange: read_name(buf, start + 2)?.0,
+        },
+        TYPE_PTR => RecordData::Ptr(read_name(buf, start)?.0),
+        TYPE_SRV => RecordData::Srv {
+            port: read_u16(buf, start + 4)?,
+            target: read_name(buf, start + 6)?.0,
+        },
+        TYPE_TXT => {
+            let mut strings = Vec::new();
+            let mut pos = 0;
+            while pos < rdata.len() {
+                let str_len = rdata[pos] as usize;
+                let value = rdata.get(pos + 1..pos + 1 + str_len)?;
+                if !value.is_empty() {
+                    strings.push(String::from_utf8_lossy(value).to_string());
+                }
+                pos += 1 + str_len;
+            }
+            RecordData::Txt(strings)
+        }
+        _ => RecordData::Other(rtype),
+    };
+    Some(data)
+}
+
+pub(crate) fn parse_message(buf: &[u8]) -> Option<DnsMessage> {
+    let id = read_u16(buf, 0)?;
+    let recursion_available = buf.get(3)? & 0x80 != 0;
+    let rcode = buf.get(3)? & 0x0F;
+    let question_count = read_u16(buf, 4)?;
+    let record_count = read_u16(buf, 6)? as usize + read_u16(buf, 8)? as usize + read_u16(buf, 10)? as usize;
+
+    let mut pos = 12;
+    for _ in 0..question_count {
+        let (_, next) = read_name(buf, pos)?;
+        pos = next + 4;
+    }
+
+    let mut records = Vec::with_capacity(record_count);
+    for _ in 0..record_count {
+        let (name, next) = read_name(buf, pos)?;
+        let rtype = read_u16(buf, next)?;
+        let rdlength = read_u16(buf, next + 8)? as usize;
+        let data = parse_rdata(buf, rtype, next + 10, rdlength)?;
+        records.push(DnsRecord { name, data });
+        pos = next + 10 + rdlength;
+    }
+
+    Some(DnsMessage { id, recursion_available, rcode, records })
+}
+
+fn system_nameserver() -> io::Result<String> {
+    std::fs::read_to_string("/etc/resolv.conf")?
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("nameserver").map(|server| server.trim().to_string()))
+        .map(|server| match server.parse::<Ipv6Addr>() {
+            Ok(_) => format!("[{}]:53", server),
+            Err(_) => format!("{}:53", server),
+        })
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no nameserver in /etc/resolv.conf"))
+}
+
+/// Authoritative nameservers of the zone containing `domain`, walking up the labels until
+/// the system resolver returns NS records.
+async fn nameservers(domain: &str, timeout: Duration) -> io::Result<(String, Vec<String>)> {
+    let resolver = system_nameserver()?;
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    let mut buffer = [0u8; 4096];
+    let mut zone = domain.trim_end_matches('.');
+
+    loop {
+        socket.send_to(&build_query(rand::random(), zone, TYPE_NS, true), &resolver).await?;
+        let (n, _) = tokio::time::timeout(timeout, socket.recv_from(&mut buffer))
+            .await
+            .map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "resolver did not answer"))??;
+
+        let servers: Vec<String> = parse_message(&buffer[..n])
+            .map(|message| message.records)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|record| record.name.eq_ignore_ascii_case(zone))
+            .filter_map(|record| match record.data {
+                RecordData::Ns(server) => Some(server),
+                _ => None,
+            })
+            .collect();
+
+        if !servers.is_empty() {
+            return Ok((zone.to_string(), servers));
+        }
+        match zone.split_once('.') {
+            Some((_, parent)) if parent.contains('.') => zone = parent,
+            _ => return Err(io::Error::new(io::ErrorKind::NotFound, "no NS records found")),
+        }
+    }
+}
+
+/// Outcome of a zone transfer against one nameserver.
+pub enum AxfrResult {
+    Transferred(Vec<DnsRecord>),
+    Refused,
+}
+
+async fn read_tcp_message(stream: &mut TcpStream) -> io::Result<Vec<u8>> {
+    let len = stream.read_u16().await? as usize;
+    let mut message = vec![0u8; len];
+    stream.read_exact(&mut message).await?;
+    Ok(message)
+}
+
+/// AXFR over TCP 53. The transfer is complete when the closing SOA record arrives.
+async fn axfr(zone: &str, nameserver: &str) -> io::Result<AxfrResult> {
+    let mut stream = TcpStream::connect((nameserver, 53)).await?;
+
+    let query = build_query(rand::random(), zone, TYPE_AXFR, false);
+    stream.write_u16(query.len() as u16).await?;
+    stream.write_all(&query).await?;
+
+    let mut records = Vec::new();
+    let mut soa_seen = 0;
+    while soa_seen < 2 {
+        let message = read_tcp_message(&mut stream).await?;
+        let message = parse_message(&message)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "malformed AXFR response"))?;
+
+        if message.rcode != 0 || (records.is_empty() && message.records.is_empty()) {
+            return Ok(AxfrResult::Refused);
+        }
+
+        for record in message.records {
+            if matches!(record.data, RecordData::Soa { .. }) {
+                soa_seen += 1;
+            }
+            records.push(record);
+        }
+    }
+
+    // The closing SOA repeats the opening one
+    records.pop();
+    Ok(AxfrResult::Transferred(records))
+}
+
+/// Looks up the authoritative nameservers of `domain` and tries a zone transfer against
+/// each of them, printing the outcome per nameserver.
+pub async fn try_zone_transfer(domain: &str, timeout: Duration) -> io::Result<Vec<(String, AxfrResult)>> {
+    let (zone, servers) = nameservers(domain, timeout).await?;
+    let mut outcomes = Vec::new();
+
+    for server in servers {
+        let outcome = match tokio::time::timeout(timeout, axfr(&zone, &server)).await {
+            Ok(Ok(outcome)) => outcome,
+            Ok(Err(e)) => {
+                crate::status!("{}[{}] {} => {}: {}", "[AXFR]".yellow(), zone.yellow(), server, "Failed".red(), e);
+                continue;
+            }
+            Err(_) => {
+                crate::status!("{}[{}] {} => {}", "[AXFR]".yellow(), zone.yellow(), server, "Timed out".red());
+                continue;
+            }
+        };
+
+        match &outcome {
+            AxfrResult::Refused => crate::status!("{}[{}] {} => {}", "[AXFR]".yellow(), zone.yellow(), server, "Refused".green()),
+            AxfrResult::Transferred(records) => {
+                crate::status!(
+                    "{}{} {} => {}",
+                    Severity::Critical.label(),
+                    format!("[{}]", zone).yellow(),
+                    server,
+                    format!("Zone transfer allowed, {} records", records.len()).red()
+                );
+                for record in records {
+                    crate::status!("    {} {}", record.name, record.data);
+                }
+            }
+        }
+        outcomes.push((server, outcome));
+    }
+
+    Ok(outcomes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn target_kind_recognises_bare_and_bracketed_ips() {
+        assert_eq!(target_kind("192.168.1.10"), TargetKind::Ip(ip("192.168.1.10")));
+        assert_eq!(target_kind(" 10.0.0.1 "), TargetKind::Ip(ip("10.0.0.1")));
+        assert_eq!(target_kind("fd00::1"), TargetKind::Ip(ip("fd00::1")));
+        assert_eq!(target_kind("[fd00::1]"), TargetKind::Ip(ip("fd00::1")));
+    }
+
+    #[test]
+    fn target_kind_recognises_cidr_blocks() {
+        assert_eq!(target_kind("192.168.1.0/24"), TargetKind::Cidr(ip("192.168.1.0"), 24));
+        assert_eq!(target_kind("10.0.0.1/32"), TargetKind::Cidr(ip("10.0.0.1"), 32));
+        assert_eq!(target_kind("fd00::/120"), TargetKind::Cidr(ip("fd00::"), 120));
+    }
+
+    #[test]
+    fn target_kind_recognises_domains_and_urls() {
+        assert_eq!(target_kind("Example.COM"), TargetKind::Domain("example.com".to_string()));
+        assert_eq!(target_kind("mail-1.internal_zone.example."), TargetKind::Domain("mail-1.internal_zone.example.".to_string()));
+        assert_eq!(target_kind("https://www.example.com:8443/login"), TargetKind::Domain("www.example.com".to_string()));
+        assert_eq!(target_kind("http://192.168.1.10/"), TargetKind::Ip(ip("192.168.1.10")));
+        assert_eq!(target_kind("http://[fd00::1]:8080"), TargetKind::Ip(ip("fd00::1")));
+    }
+
+    #[test]
+    fn target_kind_rejects_malformed_targets() {
+        for target in ["300.1.1.1/33", "10.0.0.0/33", "fd00::/129", "10.0.0.0/x", "", "   ", "http://", "300.1.1.1", "1.2.3", "-bad.example", "exa mple.com", "a..b"] {
+            assert_eq!(target_kind(target), TargetKind::Invalid, "{:?}", target);
+        }
+    }
+
+    #[tokio::test]
+    async fn resolve_domain_reports_bad_targets_instead_of_exiting() {
+        assert!(resolve_domain("300.1.1.1/33").await.is_err());
+        assert!(resolve_domain("10.0.0.0/24").await.is_err());
+        assert!(resolve_domain("").await.is_err());
+
+        let addresses = resolve_domain("127.0.0.1").await.unwrap();
+        assert!(matches!(addresses.ipv4, Some(IpType::V4(ip)) if ip == "127.0.0.1"));
+    }
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut encoded = Vec::new();
+        for label in name.split('.') {
+            encoded.push(label.len() as u8);
+            encoded.extend_from_slice(label.as_bytes());
+        }
+        encoded.push(0);
+        encoded
+    }
+
+    fn record(owner: &[u8], rtype: u16, rdata: &[u8]) -> Vec<u8> {
+        let mut encoded = owner.to_vec();
+        encoded.extend_from_slice(&rtype.to_be_bytes());
+        encoded.extend_from_slice(&CLASS_IN.to_be_bytes());
+        encoded.extend_from_slice(&3600u32.to_be_bytes());
+        encoded.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        encoded.extend_from_slice(rdata);
+        encoded
+    }
+
+    // Pointer to the zone name in the question, which starts right after the header
+    const ZONE: [u8; 2] = [0xC0, 12];
+
+    /// A zone transfer answer for example.com with compressed owner names and rdata.
+    fn axfr_response() -> Vec<u8> {
+        let mut packet = vec![0x12, 0x34, 0x84, 0x80, 0, 1, 0, 5, 0, 0, 0, 0];
+        packet.extend(name("example.com"));
+        packet.extend_from_slice(&TYPE_AXFR.to_be_bytes());
+        packet.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+        let mut soa = [&[2, b'n', b's', 0xC0, 12][..], &ZONE].concat();
+        soa.extend_from_slice(&2024010101u32.to_be_bytes());
+        soa.extend_from_slice(&[0; 16]);
+        packet.extend(record(&ZONE, TYPE_SOA, &soa));
+        packet.extend(record(&[&[3, b'w', b'w', b'w'][..], &ZONE].concat(), TYPE_A, &[192, 0, 2, 10]));
+        packet.extend(record(&ZONE, TYPE_NS, &[2, b'n', b's', 0xC0, 12]));
+        packet.extend(record(&ZONE, TYPE_MX, &[&[0, 10, 4, b'm', b'a', b'i', b'l'][..], &ZONE].concat()));
+        packet.extend(record(&ZONE, TYPE_TXT, b"\x0bv=spf1 -all"));
+        packet
+    }
+
+    #[test]
+    fn read_name_follows_compression_pointers() {
+        let packet = axfr_response();
+        assert_eq!(read_name(&packet, 12), Some(("example.com".to_string(), 25)));
+        // The owner of the A record is `www` followed by a pointer; parsing resumes after the pointer
+        let www = packet.windows(4).position(|window| window == [3, b'w', b'w', b'w']).unwrap();
+        assert_eq!(read_name(&packet, www), Some(("www.example.com".to_string(), www + 6)));
+    }
+
+    #[test]
+    fn read_name_rejects_pointer_loops() {
+        // A pointer to itself, and two pointers to each other
+        assert_eq!(read_name(&[0xC0, 0], 0), None);
+        assert_eq!(read_name(&[0xC0, 2, 0xC0, 0], 0), None);
+    }
+
+    #[test]
+    fn read_name_rejects_truncated_input() {
+        assert_eq!(read_name(&[7, b'e', b'x', b'a'], 0), None);
+        assert_eq!(read_name(&[3, b'c', b'o', b'm'], 0), None);
+        assert_eq!(read_name(&[3, b'c', b'o', b'm', 0xC0], 0), None);
+        assert_eq!(read_name(&[0xC0, 40], 0), None);
+    }
+
+    #[test]
+    fn parse_message_reads_a_zone_transfer() {
+        let message = parse_message(&axfr_response()).unwrap();
+        assert_eq!(message.id, 0x1234);
+        assert!(message.recursion_available);
+        assert_eq!(message.rcode, 0);
+
+        let records: Vec<String> = message.records.iter().map(|record| format!("{} {}", record.name, record.data)).collect();
+        assert_eq!(
+            records,
+            vec![
+                "example.com SOA ns.example.com (serial 2024010101)",
+                "www.example.com A 192.0.2.10",
+                "example.com NS ns.example.com",
+                "example.com MX 10 mail.example.com",
+                "example.com TXT \"v=spf1 -all\"",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_message_rejects_truncated_messages() {
+        let packet = axfr_response();
+        assert!(parse_message(&packet[..packet.len() - 3]).is_none());
+        assert!(parse_message(&packet[..11]).is_none());
+    }
+
+    #[test]
+    fn parse_message_rejects_a_looping_owner_name() {
+        let mut packet = vec![0, 1, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+        packet.extend(record(&[0xC0, 12], TYPE_A, &[192, 0, 2, 1]));
+        assert!(parse_message(&packet).is_none());
+    }
+}
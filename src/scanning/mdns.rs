@@ -0,0 +1,127 @@
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use colored::Colorize;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+use crate::scanning::dns::{build_query, parse_message, RecordData, TYPE_PTR, TYPE_SRV, TYPE_TXT};
+
+const MDNS_ADDR: &str = "224.0.0.251:5353";
+const SERVICES_QUERY: &str = "_services._dns-sd._udp.local";
+
+#[derive(Debug, Clone)]
+pub struct MdnsService {
+    pub service_type: String,
+    pub instance_name: String,
+    pub hostname: String,
+    pub port: u16,
+    pub txt_records: HashMap<String, String>,
+}
+
+fn parse_txt(entries: &[String]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .map(|entry| match entry.split_once('=') {
+            Some((key, value)) => (key.to_string(), value.to_string()),
+            None => (entry.clone(), String::new()),
+        })
+        .collect()
+}
+
+fn print_service(service: &MdnsService) {
+    let txt = service
+        .txt_records
+        .iter()
+        .map(|(key, value)| format!("{}={}", key, value))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    println!(
+        "{}{} {} => {}: {}:{} => {}: {}",
+        "[MDNS]".green(),
+        format!("[{}]", service.service_type).yellow(),
+        service.instance_name.yellow(),
+        "Host".green(),
+        service.hostname,
+        service.port,
+        "TXT".green(),
+        txt
+    );
+}
+
+/// Queries the local link for DNS-SD service types and their instances, printing each
+/// service as soon as its SRV record arrives.
+///
+/// Queries are sent from an ephemeral port, so responders answer with unicast (RFC 6762 6.7)
+/// and webshot does not need to bind 5353 next to a running mDNS daemon.
+pub async fn discover(listen: Duration) -> std::io::Result<Vec<MdnsService>> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.send_to(&build_query(0, SERVICES_QUERY, TYPE_PTR, false), MDNS_ADDR).await?;
+
+    let deadline = Instant::now() + listen;
+    let mut queried: HashSet<String> = HashSet::new();
+    let mut instances: HashMap<String, String> = HashMap::new();
+    let mut txt: HashMap<String, HashMap<String, String>> = HashMap::new();
+    let mut services: Vec<MdnsService> = Vec::new();
+    let mut buffer = [0u8; 9000];
+
+    while let Ok(received) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await {
+        let n = match received {
+            Ok((n, _)) => n,
+            Err(_) => continue,
+        };
+
+        let Some(message) = parse_message(&buffer[..n]) else {
+            continue;
+        };
+
+        let mut follow_ups = Vec::new();
+        let mut srv_records = Vec::new();
+
+        for record in message.records {
+            match record.data {
+                RecordData::Ptr(target) if record.name.eq_ignore_ascii_case(SERVICES_QUERY) => {
+                    let first_seen = queried.insert(target.clone());
+                    if first_seen {
+                        follow_ups.push((target, TYPE_PTR));
+                    }
+                }
+                RecordData::Ptr(instance) if !instances.contains_key(&instance) => {
+                    instances.insert(instance.clone(), record.name.clone());
+                    follow_ups.push((instance.clone(), TYPE_SRV));
+                    follow_ups.push((instance, TYPE_TXT));
+                }
+                RecordData::Txt(entries) => {
+                    txt.insert(record.name, parse_txt(&entries));
+                }
+                RecordData::Srv { port, target } => srv_records.push((record.name, port, target)),
+                _ => {}
+            }
+        }
+
+        for (instance, port, hostname) in srv_records {
+            if services.iter().any(|service| service.instance_name == instance) {
+                continue;
+            }
+
+            let service_type = instances.get(&instance).cloned().unwrap_or_else(|| {
+                instance.split_once('.').map(|(_, kind)| kind.to_string()).unwrap_or_default()
+            });
+
+            let service = MdnsService {
+                service_type,
+                instance_name: instance.clone(),
+                hostname,
+                port,
+                txt_records: txt.get(&instance).cloned().unwrap_or_default(),
+            };
+            print_service(&service);
+            services.push(service);
+        }
+
+        for (name, qtype) in follow_ups {
+            socket.send_to(&build_query(0, &name, qtype, false), MDNS_ADDR).await?;
+        }
+    }
+
+    Ok(services)
+}
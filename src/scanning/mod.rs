@@ -1,2 +1,17 @@
 pub mod tcp;
-pub mod dns;
\ No newline at end of file
+pub mod dns;
+pub mod analysis;
+pub mod mdns;
+pub mod scanner;
+pub mod protocols;
+pub mod proxy;
+pub mod types;
+pub mod output;
+pub mod profiles;
+pub mod plugins;
+pub mod checkpoint;
+pub mod replay;
+pub mod nmap;
+pub mod top_ports;
+#[cfg(test)]
+pub(crate) mod testing;
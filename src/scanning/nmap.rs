@@ -0,0 +1,363 @@
+use std::fmt;
+use std::sync::OnceLock;
+use regex::bytes::{Captures, Regex, RegexBuilder};
+
+/// The probes file shipped with webshot, used unless `--nmap-probes` names another one.
+const BUNDLED_PROBES: &str = include_str!("../service-probe/nmap-service-probe.txt");
+
+static PROBES: OnceLock<Vec<NmapProbe>> = OnceLock::new();
+
+/// A `match` or `softmatch` line. Soft matches only name the service and are tried after
+/// every hard match failed.
+#[derive(Debug)]
+pub struct NmapMatch {
+    pub service: String,
+    pub soft: bool,
+    pub regex: Regex,
+    pub templates: VersionTemplates,
+}
+
+/// The version fields of a match line, still holding `$1`, `$P(1)`, `$SUBST(1,"a","b")`
+/// and `$I(1,">")` placeholders for the regex groups.
+#[derive(Debug, Default)]
+pub struct VersionTemplates {
+    pub product: Option<String>,
+    pub version: Option<String>,
+    pub info: Option<String>,
+    pub hostname: Option<String>,
+    pub os: Option<String>,
+    pub cpe: Vec<String>,
+}
+
+/// What a match line tells about a service once its templates are filled in.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceVersion {
+    pub service: String,
+    pub product: Option<String>,
+    pub version: Option<String>,
+    pub info: Option<String>,
+    pub hostname: Option<String>,
+    pub os: Option<String>,
+    /// Vendor part of the first application CPE (`cpe:/a:<vendor>:<product>...`)
+    pub vendor: Option<String>,
+}
+
+impl fmt::Display for ServiceVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.service)?;
+        for value in [&self.product, &self.version].into_iter().flatten() {
+            write!(f, " {}", value)?;
+        }
+
+        let mut details: Vec<String> = self.info.iter().cloned().collect();
+        details.extend(self.os.as_ref().map(|os| format!("OS: {}", os)));
+        details.extend(self.hostname.as_ref().map(|hostname| format!("host: {}", hostname)));
+        details.extend(self.vendor.as_ref().map(|vendor| format!("vendor: {}", vendor)));
+        if !details.is_empty() {
+            write!(f, " ({})", details.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+/// `$SUBST`, `$P` and `$I` placeholders, and plain `$1`
+fn placeholder_regex() -> &'static regex::Regex {
+    static PLACEHOLDER: OnceLock<regex::Regex> = OnceLock::new();
+    PLACEHOLDER.get_or_init(|| {
+        regex::Regex::new(r#"\$(?:(\d)|P\((\d)\)|SUBST\((\d),"([^"]*)","([^"]*)"\)|I\((\d),"([<>])"\))"#).unwrap()
+    })
+}
+
+/// Fills the placeholders of a template with the groups the pattern captured.
+fn substitute(template: &str, captures: &Captures) -> String {
+    let group = |index: &str| captures.get(index.parse().unwrap_or(0)).map(|group| group.as_bytes()).unwrap_or_default();
+
+    placeholder_regex()
+        .replace_all(template, |placeholder: &regex::Captures| {
+            if let Some(index) = placeholder.get(1) {
+                String::from_utf8_lossy(group(index.as_str())).to_string()
+            } else if let Some(index) = placeholder.get(2) {
+                // Printable ASCII only
+                group(index.as_str()).iter().filter(|byte| byte.is_ascii_graphic() || **byte == b' ').map(|&byte| byte as char).collect()
+            } else if let Some(index) = placeholder.get(3) {
+                String::from_utf8_lossy(group(index.as_str())).replace(&placeholder[4], &placeholder[5])
+            } else {
+                // An unsigned integer in the given byte order
+                let bytes = group(&placeholder[6]);
+                let fold = |value: u64, byte: &u8| value << 8 | *byte as u64;
+                match &placeholder[7] {
+                    ">" => bytes.iter().fold(0, fold).to_string(),
+                    _ => bytes.iter().rev().fold(0, fold).to_string(),
+                }
+            }
+        })
+        .trim()
+        .to_string()
+}
+
+impl NmapMatch {
+    fn fill(&self, captures: &Captures) -> ServiceVersion {
+        let fill = |template: &Option<String>| template.as_ref().map(|template| substitute(template, captures)).filter(|value| !value.is_empty());
+
+        let vendor = self
+            .templates
+            .cpe
+            .iter()
+            .find_map(|cpe| cpe.strip_prefix("a:"))
+            .and_then(|cpe| cpe.split(':').next())
+            .map(|vendor| substitute(vendor, captures))
+            .filter(|vendor| !vendor.is_empty());
+
+        ServiceVersion {
+            service: self.service.clone(),
+            product: fill(&self.templates.product),
+            version: fill(&self.templates.version),
+            info: fill(&self.templates.info),
+            hostname: fill(&self.templates.hostname),
+            os: fill(&self.templates.os),
+            vendor,
+        }
+    }
+}
+
+/// A `Probe` directive with the match lines that follow it. Webshot does not send the
+/// probe payloads, it only matches what a service answered.
+#[derive(Debug)]
+pub struct NmapProbe {
+    pub protocol: String,
+    pub matches: Vec<NmapMatch>,
+}
+
+/// Splits `<delimiter>value<delimiter>rest` at the delimiter the value starts with, which is
+/// how nmap writes patterns (`m|...|`) and templates (`p/.../`).
+fn delimited(text: &str) -> Option<(&str, &str)> {
+    let delimiter = text.chars().next()?;
+    let body = &text[delimiter.len_utf8()..];
+    let end = body.find(delimiter)?;
+    Some((&body[..end], &body[end + delimiter.len_utf8()..]))
+}
+
+/// Rewrites the PCRE escapes the regex crate does not know: `\0` is a NUL byte in nmap patterns.
+fn translate_pattern(pattern: &str) -> String {
+    let mut translated = String::with_capacity(pattern.len());
+    let mut chars = pattern.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            translated.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') if !chars.peek().is_some_and(char::is_ascii_digit) => translated.push_str("\\x00"),
+            Some(next) => {
+                translated.push('\\');
+                translated.push(next);
+            }
+            None => translated.push('\\'),
+        }
+    }
+    translated
+}
+
+/// Parses `match <service> m<d>pattern<d>[flags] [p/product/ v/version/ ...]`. Lines whose pattern uses
+/// PCRE features the regex crate lacks (look-around, back-references) are skipped with `Ok(None)`.
+fn parse_match(line: &str, soft: bool) -> Result<Option<NmapMatch>, String> {
+    let (service, rest) = line.split_once(' ').ok_or("missing pattern")?;
+    let pattern = rest.strip_prefix('m').ok_or_else(|| format!("pattern of {} does not start with m", service))?;
+    let (pattern, rest) = delimited(pattern).ok_or_else(|| format!("unterminated pattern for {}", service))?;
+    let flags_end = rest.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(rest.len());
+    let (flags, mut templates) = rest.split_at(flags_end);
+
+    let Ok(regex) = RegexBuilder::new(&translate_pattern(pattern))
+        .unicode(false)
+        .case_insensitive(flags.contains('i'))
+        .dot_matches_new_line(flags.contains('s'))
+        .build()
+    else {
+        return Ok(None);
+    };
+
+    let mut fields = VersionTemplates::default();
+    while let Some(start) = templates.find(|c: char| !c.is_whitespace()) {
+        templates = &templates[start..];
+        let field_end = templates.find(|c: char| !c.is_ascii_alphabetic() && c != ':').unwrap_or(templates.len());
+        let (field, rest) = templates.split_at(field_end);
+        let (value, rest) = delimited(rest).ok_or_else(|| format!("unterminated {} field for {}", field, service))?;
+        let value = value.to_string();
+        match field {
+            "p" => fields.product = Some(value),
+            "v" => fields.version = Some(value),
+            "i" => fields.info = Some(value),
+            "h" => fields.hostname = Some(value),
+            "o" => fields.os = Some(value),
+            "cpe:" => fields.cpe.push(value),
+            _ => {}
+        }
+        // cpe:/.../ may carry an `a` flag after the closing delimiter
+        templates = rest.trim_start_matches(|c: char| c.is_ascii_alphabetic());
+    }
+
+    Ok(Some(NmapMatch { service: service.to_string(), soft, regex, templates: fields }))
+}
+
+/// Parses an `nmap-service-probes` file. Directives webshot has no use for (ports,
+/// rarity, fallback, ...) are ignored; a malformed match line is an error naming its line.
+pub fn parse(content: &str) -> Result<Vec<NmapProbe>, String> {
+    let mut probes: Vec<NmapProbe> = Vec::new();
+
+    for (number, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if let Some(rest) = line.strip_prefix("Probe ") {
+            let protocol = rest.split(' ').next().unwrap_or_default();
+            probes.push(NmapProbe { protocol: protocol.to_string(), matches: Vec::new() });
+            continue;
+        }
+
+        let parsed = match (line.strip_prefix("match "), line.strip_prefix("softmatch ")) {
+            (Some(rest), _) => parse_match(rest, false),
+            (_, Some(rest)) => parse_match(rest, true),
+            _ => continue,
+        };
+        let line_error = |e: String| format!("line {}: {}", number + 1, e);
+        let probe = probes.last_mut().ok_or_else(|| line_error("match before the first Probe directive".to_string()))?;
+        if let Some(parsed) = parsed.map_err(line_error)? {
+            probe.matches.push(parsed);
+        }
+    }
+    Ok(probes)
+}
+
+/// Replaces the bundled probes with the ones in `path`. Has to run before the first lookup.
+pub fn load(path: &str) -> Result<usize, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let probes = parse(&content).map_err(|e| format!("{}: {}", path, e))?;
+    if probes.is_empty() {
+        return Err(format!("{}: no Probe directives found", path));
+    }
+
+    let count = probes.iter().map(|probe| probe.matches.len()).sum();
+    PROBES.set(probes).map_err(|_| "service probes are already loaded".to_string())?;
+    Ok(count)
+}
+
+/// The parsed probes, from the file given to `load` or the bundled one. Parsing compiles
+/// every pattern, so it happens once and is shared by all scan tasks.
+pub fn probes() -> &'static [NmapProbe] {
+    PROBES.get_or_init(|| parse(BUNDLED_PROBES).expect("the bundled probes file is well formed"))
+}
+
+/// The first match of a TCP probe for a banner, trying every hard match before any soft match.
+fn find_match(banner: &[u8]) -> Option<&'static NmapMatch> {
+    let matches = || probes().iter().filter(|probe| probe.protocol == "TCP").flat_map(|probe| &probe.matches);
+    matches()
+        .filter(|candidate| !candidate.soft)
+        .find(|candidate| candidate.regex.is_match(banner))
+        .or_else(|| matches().filter(|candidate| candidate.soft).find(|candidate| candidate.regex.is_match(banner)))
+}
+
+/// Names the service behind a banner and fills in the version fields of the matching line.
+pub fn fingerprint_service(banner: &[u8]) -> Option<ServiceVersion> {
+    let found = find_match(banner)?;
+    let captures = found.regex.captures(banner)?;
+    Some(found.fill(&captures))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_one(line: &str) -> NmapMatch {
+        let probes = parse(&format!("Probe TCP NULL q||\n{}", line)).unwrap();
+        probes.into_iter().next().unwrap().matches.into_iter().next().unwrap_or_else(|| panic!("{} was skipped", line))
+    }
+
+    fn fill(line: &str, banner: &[u8]) -> ServiceVersion {
+        let found = parse_one(line);
+        let captures = found.regex.captures(banner).unwrap();
+        found.fill(&captures)
+    }
+
+    #[test]
+    fn parses_match_lines_and_their_fields() {
+        let found = parse_one(r#"match ftp m/^220 ([\w.-]+) FTP server \(Version ([\d.]+)\)/i p/BSD ftpd/ v/$2/ h/$1/ o/FreeBSD/ cpe:/a:freebsd:ftpd:$2/a"#);
+        assert_eq!(found.service, "ftp");
+        assert!(!found.soft);
+        assert_eq!(found.templates.product.as_deref(), Some("BSD ftpd"));
+        assert_eq!(found.templates.version.as_deref(), Some("$2"));
+        assert_eq!(found.templates.hostname.as_deref(), Some("$1"));
+        assert_eq!(found.templates.os.as_deref(), Some("FreeBSD"));
+        assert_eq!(found.templates.cpe, ["a:freebsd:ftpd:$2"]);
+        // The i flag makes the pattern case insensitive
+        assert!(found.regex.is_match(b"220 HOST ftp SERVER (version 6.00)"));
+
+        let soft = parse_one("softmatch smtp m|^220 .*smtp|i");
+        assert!(soft.soft);
+        assert_eq!(soft.templates.product, None);
+    }
+
+    #[test]
+    fn patterns_and_templates_take_any_delimiter() {
+        for line in ["match ssh m|^SSH-([\\d.]+)-| p|OpenSSH|", "match ssh m=^SSH-([\\d.]+)-= p=OpenSSH=", "match ssh m%^SSH-([\\d.]+)-% p%OpenSSH%"] {
+            let found = parse_one(line);
+            assert!(found.regex.is_match(b"SSH-2.0-OpenSSH_9.6"), "{}", line);
+            assert_eq!(found.templates.product.as_deref(), Some("OpenSSH"), "{}", line);
+        }
+        // The s flag lets . cross lines, and \0 is a NUL byte
+        assert!(parse_one(r"match x m|^a.b\0|s").regex.is_match(b"a\nb\0"));
+    }
+
+    #[test]
+    fn templates_are_filled_from_the_captured_groups() {
+        let version = fill(r#"match ssh m|^SSH-[\d.]+-OpenSSH_([\w._-]+) ([^\r\n]+)| p/OpenSSH/ v/$SUBST(1,"_"," ")/ i/$P(2)/ cpe:/a:openbsd:openssh:$1/"#, b"SSH-2.0-OpenSSH_9.6_p1 Ubuntu\x01\r\n");
+        assert_eq!(version.version.as_deref(), Some("9.6 p1"));
+        assert_eq!(version.info.as_deref(), Some("Ubuntu"));
+        assert_eq!(version.vendor.as_deref(), Some("openbsd"));
+
+        let version = fill(r#"match x m|^(..)(..)| v/$I(1,">")/ i/$I(2,"<")/"#, b"\x01\x02\x01\x02");
+        assert_eq!(version.version.as_deref(), Some("258"));
+        assert_eq!(version.info.as_deref(), Some("513"));
+
+        // Missing groups fill in as nothing, and empty fields are dropped
+        let version = fill(r"match x m|^(a)| p/$1$9/ v/$2/", b"a");
+        assert_eq!(version.product.as_deref(), Some("a"));
+        assert_eq!(version.version, None);
+    }
+
+    #[test]
+    fn unsupported_patterns_are_skipped() {
+        let probes = parse("Probe TCP NULL q||\nmatch x m|^(?=a)|\nmatch y m|^(a)\\1|\nmatch z m|^z|").unwrap();
+        assert_eq!(probes[0].matches.iter().map(|found| found.service.as_str()).collect::<Vec<_>>(), ["z"]);
+    }
+
+    #[test]
+    fn malformed_match_lines_are_errors() {
+        for (line, error) in [
+            ("match ssh", "missing pattern"),
+            ("match ssh |^SSH|", "does not start with m"),
+            ("match ssh m|^SSH", "unterminated pattern"),
+            ("match ssh m|^SSH| p/OpenSSH", "unterminated p field"),
+            ("softmatch ssh m", "unterminated pattern"),
+        ] {
+            let e = parse(&format!("Probe TCP NULL q||\n\n{}", line)).err().unwrap_or_else(|| panic!("{} parsed", line));
+            assert!(e.starts_with("line 3: ") && e.contains(error), "{}: {}", line, e);
+        }
+        assert!(parse("match ssh m|^SSH|").err().unwrap().contains("before the first Probe"));
+    }
+
+    #[test]
+    fn loading_a_bad_probes_file_is_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("probes.txt");
+        std::fs::write(&path, "Probe TCP NULL q||\nmatch ssh m|^SSH\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        assert_eq!(load(path).err(), Some(format!("{}: line 2: unterminated pattern for ssh", path)));
+        std::fs::write(path, "# nothing here\n").unwrap();
+        assert!(load(path).err().unwrap().contains("no Probe directives"));
+    }
+
+    #[test]
+    fn bundled_probes_parse() {
+        let probes = parse(BUNDLED_PROBES).unwrap();
+        assert!(probes.iter().any(|probe| probe.protocol == "TCP" && !probe.matches.is_empty()));
+    }
+}
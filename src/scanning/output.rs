@@ -0,0 +1,529 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use chrono::{Local, TimeDelta};
+use colored::Colorize;
+use rusqlite::{params, Connection};
+use crate::scanning::nmap::{self, ServiceVersion};
+use crate::scanning::tcp::format_result;
+use crate::scanning::types::{ScanResult, ScanSummary};
+
+/// Set when a machine-readable format is written to stdout, which then has to hold nothing else.
+pub static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Prints a banner, log or live result line like `println!`, but on stderr while
+/// `STATUS_TO_STDERR` is set.
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        match $crate::scanning::output::STATUS_TO_STDERR.load(std::sync::atomic::Ordering::Relaxed) {
+            true => eprintln!($($arg)*),
+            false => println!($($arg)*),
+        }
+    };
+}
+
+/// Renders the results of a finished scan. Each result is passed to `write_result`,
+/// then `write_summary` is called once.
+pub trait OutputWriter {
+    fn write_result(&mut self, result: &ScanResult);
+    fn write_summary(&mut self, summary: &ScanSummary);
+}
+
+/// Where the main writer sends its output: stdout, or the file given to `--output`.
+pub enum Destination {
+    Stdout,
+    File { path: PathBuf, file: File },
+}
+
+impl Destination {
+    /// Creates (or truncates) the file right away, so an unwritable path is reported
+    /// before the scan starts rather than after it.
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Destination::File { path: path.to_path_buf(), file })
+    }
+
+    fn is_file(&self) -> bool {
+        matches!(self, Destination::File { .. })
+    }
+
+    fn write(&mut self, text: &str) {
+        match self {
+            Destination::Stdout => println!("{}", text),
+            Destination::File { path, file } => {
+                if let Err(e) = writeln!(file, "{}", text) {
+                    let time = Local::now().format("%H:%M:%S").to_string();
+                    eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), format!("Could not write results to {}", path.display()).red(), e);
+                }
+            }
+        }
+    }
+}
+
+/// Default terminal output. On stdout the results were already printed live while the
+/// ports were scanned, so only the summary table is written; a file gets one line per
+/// open port before it.
+pub struct TextWriter {
+    out: Destination,
+    results: Vec<ScanResult>,
+}
+
+impl TextWriter {
+    pub fn new(out: Destination) -> Self {
+        TextWriter { out, results: Vec::new() }
+    }
+}
+
+impl OutputWriter for TextWriter {
+    fn write_result(&mut self, result: &ScanResult) {
+        if self.out.is_file() {
+            self.results.push(result.clone());
+        }
+    }
+
+    fn write_summary(&mut self, summary: &ScanSummary) {
+        // No color codes in files
+        if self.out.is_file() {
+            colored::control::set_override(false);
+        }
+        let mut lines: Vec<String> = self.results.iter().map(|result| format_result(result, None, summary.hosts_scanned > 1)).collect();
+        lines.push(summary.to_string());
+        colored::control::unset_override();
+        self.out.write(&lines.join("\n"));
+    }
+}
+
+/// Writes the scan as one JSON document: the summary counts and an array of the open ports.
+pub struct JsonWriter {
+    out: Destination,
+    results: Vec<ScanResult>,
+}
+
+impl JsonWriter {
+    pub fn new(out: Destination) -> Self {
+        JsonWriter { out, results: Vec::new() }
+    }
+}
+
+impl OutputWriter for JsonWriter {
+    fn write_result(&mut self, result: &ScanResult) {
+        self.results.push(result.clone());
+    }
+
+    fn write_summary(&mut self, summary: &ScanSummary) {
+        let started_at = (Local::now() - TimeDelta::from_std(summary.duration).unwrap_or_default()).to_rfc3339();
+        let json = serde_json::json!({
+            "target": summary.target,
+            "protocol": summary.protocol.to_lowercase(),
+            "started_at": started_at,
+            "duration_ms": summary.duration.as_millis() as u64,
+            "hosts_scanned": summary.hosts_scanned,
+            "ports_scanned": summary.ports_scanned,
+            "open_ports": summary.open_ports,
+            "errored_ports": summary.errored_ports,
+            "results": self.results.iter().map(ScanResult::to_json).collect::<Vec<_>>(),
+        });
+        self.out.write(&serde_json::to_string_pretty(&json).unwrap_or_default());
+    }
+}
+
+/// Prints the open ports as one comma-separated line (`22,80`), optionally with a
+/// protocol suffix (`22/tcp,80/tcp`).
+pub struct PortsCsvWriter {
+    out: Destination,
+    with_protocol: bool,
+    entries: Vec<(u16, &'static str)>,
+}
+
+impl PortsCsvWriter {
+    pub fn new(with_protocol: bool, out: Destination) -> Self {
+        PortsCsvWriter { out, with_protocol, entries: Vec::new() }
+    }
+}
+
+impl OutputWriter for PortsCsvWriter {
+    fn write_result(&mut self, result: &ScanResult) {
+        self.entries.push((result.port, result.protocol));
+    }
+
+    fn write_summary(&mut self, _summary: &ScanSummary) {
+        self.entries.sort_unstable();
+        self.entries.dedup();
+
+        let line = self
+            .entries
+            .iter()
+            .map(|(port, protocol)| match self.with_protocol {
+                true => format!("{}/{}", port, protocol.to_lowercase()),
+                false => port.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        self.out.write(&line);
+    }
+}
+
+// RFC 4180: fields holding a separator, quote or line break are quoted, quotes doubled
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\r', '\n']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+/// Prints one CSV row per open port, for spreadsheets and other tabular tools.
+pub struct CsvWriter {
+    out: Destination,
+    /// The target's name when it was given as a domain
+    hostname: Option<String>,
+    results: Vec<ScanResult>,
+}
+
+impl CsvWriter {
+    pub fn new(out: Destination, hostname: Option<String>) -> Self {
+        CsvWriter { out, hostname, results: Vec::new() }
+    }
+}
+
+impl OutputWriter for CsvWriter {
+    fn write_result(&mut self, result: &ScanResult) {
+        self.results.push(result.clone());
+    }
+
+    fn write_summary(&mut self, summary: &ScanSummary) {
+        let started_at = (Local::now() - TimeDelta::from_std(summary.duration).unwrap_or_default()).to_rfc3339();
+        let hostname = self.hostname.clone().unwrap_or_default();
+
+        let mut rows = vec!["port,protocol,state,service,banner,target_ip,hostname,timestamp".to_string()];
+        rows.extend(self.results.iter().map(|result| {
+            let target = result.target_ip.map(|ip| ip.to_string()).unwrap_or_else(|| summary.target.clone());
+            [result.port.to_string(), result.protocol.to_lowercase(), "open".to_string(), result.service.clone(), result.banner.clone(), target, hostname.clone(), started_at.clone()]
+                .iter()
+                .map(|field| csv_field(field))
+                .collect::<Vec<_>>()
+                .join(",")
+        }));
+        // Records end in CRLF, as RFC 4180 asks
+        self.out.write(&rows.join("\r\n"));
+    }
+}
+
+// Attribute values are quoted and banners carry line breaks and control bytes, which
+// XML 1.0 does not allow even as character references
+fn xml_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#xa;"),
+            '\r' => escaped.push_str("&#xd;"),
+            '\t' => escaped.push_str("&#x9;"),
+            c if c.is_control() => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Prints the scan as nmap XML (`-oX`), so tools that read `.nmap.xml` files can load
+/// webshot results. Only open ports are listed; the others are counted in `<extraports>`.
+pub struct XmlWriter {
+    out: Destination,
+    results: Vec<ScanResult>,
+}
+
+impl XmlWriter {
+    pub fn new(out: Destination) -> Self {
+        XmlWriter { out, results: Vec::new() }
+    }
+
+    fn port(result: &ScanResult, version: Option<&ServiceVersion>) -> String {
+        let attribute = |name: &str, value: &Option<String>| value.as_ref().map(|value| format!(" {}=\"{}\"", name, xml_escape(value))).unwrap_or_default();
+        let service = match version {
+            Some(version) => format!(
+                "<service name=\"{}\"{}{}{}{}{} method=\"probed\" conf=\"10\"/>",
+                xml_escape(&version.service),
+                attribute("product", &version.product),
+                attribute("version", &version.version),
+                attribute("extrainfo", &version.info),
+                attribute("hostname", &version.hostname),
+                attribute("ostype", &version.os)
+            ),
+            // Not named by the nmap probes (UDP, the HTTP probe): only the service column is known
+            None => {
+                let (name, product) = result.service.split_once(' ').unwrap_or((&result.service, ""));
+                let product = Some(product.trim().to_string()).filter(|product| !product.is_empty());
+                format!("<service name=\"{}\"{} method=\"table\" conf=\"3\"/>", xml_escape(&name.to_lowercase()), attribute("product", &product))
+            }
+        };
+        let banner = match result.banner.is_empty() {
+            true => String::new(),
+            false => format!("<script id=\"banner\" output=\"{}\"/>", xml_escape(&result.banner)),
+        };
+
+        format!(
+            "<port protocol=\"{}\" portid=\"{}\"><state state=\"open\" reason=\"{}\" reason_ttl=\"0\"/>{}{}</port>",
+            result.protocol.to_lowercase(),
+            result.port,
+            if result.protocol == "TCP" { "syn-ack" } else { "udp-response" },
+            service,
+            banner
+        )
+    }
+
+    /// `<osmatch>` guesses from the OS the services named, with the share of those
+    /// services that agree as the accuracy. Webshot does no TCP/IP stack fingerprinting.
+    fn os(versions: &[Option<ServiceVersion>]) -> Option<String> {
+        let named: Vec<&str> = versions.iter().flatten().filter_map(|version| version.os.as_deref()).collect();
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for os in &named {
+            match counts.iter_mut().find(|(counted, _)| counted == os) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((os, 1)),
+            }
+        }
+        counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+
+        let matches: Vec<String> = counts
+            .iter()
+            .enumerate()
+            .map(|(line, (os, count))| format!("<osmatch name=\"{}\" accuracy=\"{}\" line=\"{}\"/>", xml_escape(os), 100 * count / named.len(), line))
+            .collect();
+        match matches.is_empty() {
+            true => None,
+            false => Some(format!("<os>{}</os>", matches.concat())),
+        }
+    }
+}
+
+impl OutputWriter for XmlWriter {
+    fn write_result(&mut self, result: &ScanResult) {
+        self.results.push(result.clone());
+    }
+
+    fn write_summary(&mut self, summary: &ScanSummary) {
+        let finished = Local::now();
+        let started = finished - TimeDelta::from_std(summary.duration).unwrap_or_default();
+        let protocol = summary.protocol.to_lowercase();
+
+        let host_of = |result: &ScanResult| result.target_ip.map(|ip| ip.to_string()).unwrap_or_else(|| summary.target.clone());
+        let mut hosts: Vec<String> = Vec::new();
+        for host in self.results.iter().map(host_of) {
+            if !hosts.contains(&host) {
+                hosts.push(host);
+            }
+        }
+
+        let mut lines = Vec::new();
+        lines.push("<?xml version=\"1.0\" encoding=\"UTF-8\"?>".to_string());
+        lines.push("<!DOCTYPE nmaprun>".to_string());
+        lines.push(format!(
+            "<nmaprun scanner=\"webshot\" args=\"{}\" start=\"{}\" startstr=\"{}\" version=\"{}\" xmloutputversion=\"1.05\">",
+            xml_escape(&std::env::args().collect::<Vec<_>>().join(" ")),
+            started.timestamp(),
+            started.format("%a %b %e %H:%M:%S %Y"),
+            env!("CARGO_PKG_VERSION")
+        ));
+        lines.push(format!("<scaninfo type=\"{}\" protocol=\"{}\" numservices=\"{}\"/>", if protocol == "tcp" { "connect" } else { "udp" }, protocol, summary.ports_scanned));
+
+        for host in &hosts {
+            let ports: Vec<&ScanResult> = self.results.iter().filter(|result| host_of(result) == *host).collect();
+            let addrtype = if host.contains(':') { "ipv6" } else { "ipv4" };
+
+            lines.push(format!("<host starttime=\"{}\" endtime=\"{}\"><status state=\"up\" reason=\"{}\" reason_ttl=\"0\"/>", started.timestamp(), finished.timestamp(), if protocol == "tcp" { "syn-ack" } else { "udp-response" }));
+            lines.push(format!("<address addr=\"{}\" addrtype=\"{}\"/>", xml_escape(host), addrtype));
+            lines.push(format!("<ports><extraports state=\"closed\" count=\"{}\"/>", summary.ports_scanned.saturating_sub(ports.len())));
+            let versions: Vec<Option<ServiceVersion>> = ports.iter().map(|result| nmap::fingerprint_service(result.banner.as_bytes())).collect();
+            for (result, version) in ports.iter().zip(&versions) {
+                lines.push(Self::port(result, version.as_ref()));
+            }
+            lines.push("</ports>".to_string());
+            lines.extend(Self::os(&versions));
+            lines.push("</host>".to_string());
+        }
+
+        lines.push(format!("<runstats><finished time=\"{}\" timestr=\"{}\" elapsed=\"{:.2}\" summary=\"{} host(s) scanned, {} open port(s)\" exit=\"success\"/>", finished.timestamp(), finished.format("%a %b %e %H:%M:%S %Y"), summary.duration.as_secs_f64(), summary.hosts_scanned, summary.open_ports));
+        lines.push(format!("<hosts up=\"{}\" down=\"{}\" total=\"{}\"/>", hosts.len(), summary.hosts_scanned.saturating_sub(hosts.len()), summary.hosts_scanned));
+        lines.push("</runstats>".to_string());
+        lines.push("</nmaprun>".to_string());
+        self.out.write(&lines.join("\n"));
+    }
+}
+
+const SQLITE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS scans (
+        id INTEGER PRIMARY KEY,
+        target TEXT NOT NULL,
+        protocol TEXT NOT NULL,
+        started_at TEXT NOT NULL,
+        duration_ms INTEGER NOT NULL,
+        ports_scanned INTEGER NOT NULL,
+        open_ports INTEGER NOT NULL,
+        errored_ports INTEGER NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS results (
+        id INTEGER PRIMARY KEY,
+        scan_id INTEGER NOT NULL REFERENCES scans(id),
+        target TEXT NOT NULL,
+        port INTEGER NOT NULL,
+        protocol TEXT NOT NULL,
+        banner TEXT NOT NULL,
+        service TEXT NOT NULL,
+        scanned_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS results_target ON results(target);
+    CREATE INDEX IF NOT EXISTS results_port ON results(port);
+";
+
+/// Appends the scan to a SQLite database, creating the schema on first use. The scan
+/// and its results are written in one transaction once the summary arrives.
+pub struct SqliteWriter {
+    path: String,
+    results: Vec<ScanResult>,
+}
+
+impl SqliteWriter {
+    pub fn new(path: &str) -> Self {
+        SqliteWriter { path: path.to_string(), results: Vec::new() }
+    }
+
+    fn store(&self, summary: &ScanSummary) -> rusqlite::Result<()> {
+        let mut connection = Connection::open(&self.path)?;
+        connection.execute_batch(SQLITE_SCHEMA)?;
+
+        let started_at = (Local::now() - TimeDelta::from_std(summary.duration).unwrap_or_default()).to_rfc3339();
+        let transaction = connection.transaction()?;
+        transaction.execute(
+            "INSERT INTO scans (target, protocol, started_at, duration_ms, ports_scanned, open_ports, errored_ports)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                summary.target,
+                summary.protocol,
+                started_at,
+                summary.duration.as_millis() as i64,
+                summary.ports_scanned as i64,
+                summary.open_ports as i64,
+                summary.errored_ports as i64,
+            ],
+        )?;
+        let scan_id = transaction.last_insert_rowid();
+
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO results (scan_id, target, port, protocol, banner, service, scanned_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            )?;
+            for result in &self.results {
+                let target = result.target_ip.map(|ip| ip.to_string()).unwrap_or_else(|| summary.target.clone());
+                insert.execute(params![scan_id, target, result.port, result.protocol, result.banner, result.service, started_at])?;
+            }
+        }
+
+        transaction.commit()
+    }
+}
+
+impl OutputWriter for SqliteWriter {
+    fn write_result(&mut self, result: &ScanResult) {
+        self.results.push(result.clone());
+    }
+
+    fn write_summary(&mut self, summary: &ScanSummary) {
+        if let Err(e) = self.store(summary) {
+            let time = Local::now().format("%H:%M:%S").to_string();
+            eprintln!("[{}]{} {}: {}", time, "[ERROR]".on_red(), format!("Could not write results to {}", self.path).red(), e);
+        }
+    }
+}
+
+// Label values are quoted, so backslashes, quotes and newlines must be escaped
+fn prometheus_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Renders the summary in the Prometheus text exposition format.
+fn prometheus_metrics(summary: &ScanSummary) -> String {
+    let labels = format!("target=\"{}\",protocol=\"{}\"", prometheus_label(&summary.target), summary.protocol);
+    let metrics = [
+        ("webshot_scan_open_ports_total", "Open ports found by the scan", summary.open_ports as f64),
+        ("webshot_scan_duration_seconds", "Wall-clock duration of the scan", summary.duration.as_secs_f64()),
+        ("webshot_scan_total_ports_scanned", "Ports the scan covered", summary.ports_scanned as f64),
+    ];
+
+    metrics
+        .iter()
+        .map(|(name, help, value)| format!("# HELP {} {}\n# TYPE {} gauge\n{}{{{}}} {}\n", name, help, name, name, labels, value))
+        .collect()
+}
+
+/// Pushes the summary of a one-shot scan to a Prometheus Pushgateway, grouped under
+/// `job="webshot"` and the scan id so that consecutive scans do not overwrite each other.
+pub async fn push_to_gateway(url: &str, summary: &ScanSummary, scan_id: &str) -> Result<(), String> {
+    let endpoint = format!("{}/metrics/job/webshot/scan_id/{}", url.trim_end_matches('/'), scan_id);
+    let response = reqwest::Client::new()
+        .post(&endpoint)
+        .header("Content-Type", "text/plain; version=0.0.4")
+        .body(prometheus_metrics(summary))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    match response.status().is_success() {
+        true => Ok(()),
+        false => Err(format!("{} answered {}", endpoint, response.status())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use chrono::DateTime;
+    use super::*;
+    use crate::scanning::scanner::ScanConfig;
+
+    #[test]
+    fn csv_fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("SSH-2.0-OpenSSH_9.6"), "SSH-2.0-OpenSSH_9.6");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("220 ready\r\n"), "\"220 ready\r\n\"");
+        assert_eq!(csv_field(""), "");
+    }
+
+    #[test]
+    fn csv_writer_writes_a_header_and_one_quoted_row_per_port() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.csv");
+        let ip = "10.0.0.5".parse().ok();
+        let results = [
+            ScanResult::new(21, "TCP", "220 \"mail\", ready\r\n".to_string(), "FTP".to_string()).with_target_ip(ip),
+            ScanResult::new(22, "TCP", "SSH-2.0-OpenSSH_9.6".to_string(), "SSH".to_string()).with_target_ip(ip),
+        ];
+        let config = ScanConfig::new("10.0.0.5".to_string(), vec![21, 22], "TCP");
+
+        let mut writer = CsvWriter::new(Destination::create(&path).unwrap(), Some("mail.example.com".to_string()));
+        for result in &results {
+            writer.write_result(result);
+        }
+        writer.write_summary(&ScanSummary::complete(&config, &results, 0, Duration::from_secs(1)));
+        drop(writer);
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        assert!(csv.starts_with("port,protocol,state,service,banner,target_ip,hostname,timestamp\r\n"));
+        assert!(csv.contains("\r\n21,tcp,open,FTP,\"220 \"\"mail\"\", ready\r\n\",10.0.0.5,mail.example.com,"), "{}", csv);
+        assert!(csv.contains("\r\n22,tcp,open,SSH,SSH-2.0-OpenSSH_9.6,10.0.0.5,mail.example.com,"), "{}", csv);
+
+        // And a CSV reader gets the banners back unchanged
+        let mut reader = csv::Reader::from_reader(csv.as_bytes());
+        let rows: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>().unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(&rows[0][4], "220 \"mail\", ready\r\n");
+        assert!(rows.iter().all(|row| row.len() == 8 && DateTime::parse_from_rfc3339(&row[7]).is_ok()));
+    }
+}
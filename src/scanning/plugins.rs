@@ -0,0 +1,163 @@
+use crate::scanning::protocols::Severity;
+use crate::scanning::types::ScanResult;
+
+/// Something a plugin noticed across the collected results, optionally tied to one port.
+#[derive(Debug, Clone)]
+pub struct PluginFinding {
+    pub port: Option<u16>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl PluginFinding {
+    pub fn new(port: Option<u16>, severity: Severity, message: impl Into<String>) -> Self {
+        PluginFinding { port, severity, message: message.into() }
+    }
+}
+
+/// Post-scan analysis over the results of a finished scan.
+pub trait ScanPlugin: Send + Sync {
+    fn name(&self) -> &str;
+    fn analyze(&self, results: &[ScanResult]) -> Vec<PluginFinding>;
+}
+
+/// The plugins run after a scan, in registration order.
+#[derive(Default)]
+pub struct PluginRegistry {
+    plugins: Vec<Box<dyn ScanPlugin>>,
+}
+
+impl PluginRegistry {
+    /// A registry with the bundled plugins already registered.
+    pub fn with_builtin() -> Self {
+        let mut registry = PluginRegistry::default();
+        registry.register(Box::new(DatabaseExposurePlugin));
+        registry.register(Box::new(WeakServicePlugin));
+        registry.register(Box::new(DefaultPortConflictPlugin));
+        registry
+    }
+
+    pub fn register(&mut self, plugin: Box<dyn ScanPlugin>) {
+        self.plugins.push(plugin);
+    }
+
+    /// Runs every plugin and returns its findings together with the plugin name.
+    pub fn run(&self, results: &[ScanResult]) -> Vec<(&str, PluginFinding)> {
+        self.plugins
+            .iter()
+            .flat_map(|plugin| plugin.analyze(results).into_iter().map(move |finding| (plugin.name(), finding)))
+            .collect()
+    }
+}
+
+/// First word of the detected service, e.g. "ssh" for "ssh OpenSSH".
+fn service_name(result: &ScanResult) -> Option<String> {
+    match result.service.split_whitespace().next() {
+        Some(name) if result.service != "Unknown" => Some(name.to_lowercase()),
+        _ => None,
+    }
+}
+
+const DATABASES: &[(u16, &str, &str)] = &[
+    (1433, "ms-sql", "Microsoft SQL Server"),
+    (1521, "oracle", "Oracle"),
+    (3306, "mysql", "MySQL"),
+    (5432, "postgresql", "PostgreSQL"),
+    (5984, "couchdb", "CouchDB"),
+    (6379, "redis", "Redis"),
+    (9042, "cassandra", "Cassandra"),
+    (9200, "elasticsearch", "Elasticsearch"),
+    (11211, "memcached", "Memcached"),
+    (27017, "mongodb", "MongoDB"),
+];
+
+/// Flags database servers that accept connections from the scanning host.
+pub struct DatabaseExposurePlugin;
+
+impl ScanPlugin for DatabaseExposurePlugin {
+    fn name(&self) -> &str {
+        "database-exposure"
+    }
+
+    fn analyze(&self, results: &[ScanResult]) -> Vec<PluginFinding> {
+        results
+            .iter()
+            .filter_map(|result| {
+                let service = service_name(result);
+                let (_, _, label) = DATABASES.iter().find(|(port, name, _)| match &service {
+                    Some(service) => service.starts_with(name),
+                    None => *port == result.port,
+                })?;
+                Some(PluginFinding::new(Some(result.port), Severity::High, format!("{} is reachable over the network", label)))
+            })
+            .collect()
+    }
+}
+
+/// Flags services that send credentials and data in cleartext.
+pub struct WeakServicePlugin;
+
+impl ScanPlugin for WeakServicePlugin {
+    fn name(&self) -> &str {
+        "weak-service"
+    }
+
+    fn analyze(&self, results: &[ScanResult]) -> Vec<PluginFinding> {
+        results
+            .iter()
+            .filter(|result| result.protocol == "TCP")
+            .filter_map(|result| {
+                let message = match (service_name(result).as_deref(), result.port) {
+                    (Some("telnet"), _) | (None, 23) => "Telnet sends credentials in cleartext",
+                    (Some("ftp"), _) | (None, 21) => "FTP sends credentials in cleartext",
+                    (Some("http"), _) | (None, 80) => "HTTP without TLS",
+                    _ => return None,
+                };
+                Some(PluginFinding::new(Some(result.port), Severity::Medium, message))
+            })
+            .collect()
+    }
+}
+
+const WELL_KNOWN_PORTS: &[(u16, &[&str])] = &[
+    (21, &["ftp"]),
+    (22, &["ssh"]),
+    (23, &["telnet"]),
+    (25, &["smtp"]),
+    (53, &["domain", "dns"]),
+    (80, &["http"]),
+    (110, &["pop3"]),
+    (143, &["imap"]),
+    (443, &["http", "https", "ssl"]),
+    (3306, &["mysql"]),
+    (5432, &["postgresql"]),
+    (6379, &["redis"]),
+];
+
+/// Flags well-known ports answered by a different service than the one registered for
+/// them, which can mean a proxy, a tunnel or a service hidden on a trusted port.
+pub struct DefaultPortConflictPlugin;
+
+impl ScanPlugin for DefaultPortConflictPlugin {
+    fn name(&self) -> &str {
+        "default-port-conflict"
+    }
+
+    fn analyze(&self, results: &[ScanResult]) -> Vec<PluginFinding> {
+        results
+            .iter()
+            .filter_map(|result| {
+                let service = service_name(result)?;
+                let (_, expected) = WELL_KNOWN_PORTS.iter().find(|(port, _)| *port == result.port)?;
+                if expected.iter().any(|name| service.starts_with(name)) {
+                    return None;
+                }
+                Some(PluginFinding::new(
+                    Some(result.port),
+                    Severity::Medium,
+                    format!("{} is expected here but the port answers as {}", expected[0], service),
+                ))
+            })
+            .collect()
+    }
+}
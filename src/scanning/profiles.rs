@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use serde_json::Value;
+
+/// A named bundle of scan settings. Unset fields fall back to the regular defaults,
+/// and explicit command line flags always win over the profile.
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    pub protocol: Option<&'static str>,
+    pub ports: Option<Vec<u16>>,
+    pub timeout: Option<Duration>,
+    pub chunk_size: Option<usize>,
+    pub min_banner_bytes: Option<usize>,
+}
+
+pub const BUILTIN_PROFILES: [&str; 3] = ["quick", "web", "db"];
+
+/// Scanned when neither a port argument nor a profile names the ports.
+pub const DEFAULT_PORT_SET: &str = "common";
+
+const FAST_PORTS: [u16; 16] = [21, 22, 23, 25, 53, 80, 110, 143, 443, 445, 993, 995, 3306, 3389, 5432, 8080];
+
+/// The port sets selectable with `--default-ports`: `fast` (16 well-known service ports),
+/// `common` (1-1024) and `full` (1-65535).
+pub fn port_set(name: &str) -> Option<Vec<u16>> {
+    match name {
+        "fast" => Some(FAST_PORTS.to_vec()),
+        "common" => Some((1..=1024).collect()),
+        "full" => Some((1..=65535).collect()),
+        _ => None,
+    }
+}
+
+pub fn builtin(name: &str) -> Option<Profile> {
+    let profile = match name {
+        "quick" => Profile {
+            protocol: Some("TCP"),
+            ports: Some(FAST_PORTS.to_vec()),
+            timeout: Some(Duration::from_secs(3)),
+            ..Profile::default()
+        },
+        "web" => Profile {
+            protocol: Some("TCP"),
+            ports: Some(vec![80, 443, 3000, 5000, 8000, 8008, 8080, 8081, 8443, 8888, 9000, 9443]),
+            timeout: Some(Duration::from_secs(10)),
+            ..Profile::default()
+        },
+        "db" => Profile {
+            protocol: Some("TCP"),
+            ports: Some(vec![1433, 1521, 3306, 5432, 5984, 6379, 7474, 9042, 9200, 11211, 27017]),
+            timeout: Some(Duration::from_secs(10)),
+            ..Profile::default()
+        },
+        _ => return None,
+    };
+    Some(profile)
+}
+
+/// Parses a port list such as `80`, `1-1024` or `22,80,8000-8100`.
+pub fn parse_ports(spec: &str) -> Result<Vec<u16>, String> {
+    let mut ports = Vec::new();
+    for part in spec.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let invalid = || format!("'{}' is not a port or port range", part);
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let start: u16 = start.trim().parse().map_err(|_| invalid())?;
+                let end: u16 = end.trim().parse().map_err(|_| invalid())?;
+                if start > end {
+                    return Err(invalid());
+                }
+                ports.extend(start..=end);
+            }
+            None => ports.push(part.parse().map_err(|_| invalid())?),
+        }
+    }
+
+    if ports.is_empty() {
+        return Err("no ports given".to_string());
+    }
+    ports.sort_unstable();
+    ports.dedup();
+    Ok(ports)
+}
+
+fn parse_profile(name: &str, value: &Value) -> Result<Profile, String> {
+    let fields = value.as_object().ok_or_else(|| format!("profile '{}' is not an object", name))?;
+    let invalid = |field: &str| format!("profile '{}': invalid {}", name, field);
+    let mut profile = Profile::default();
+
+    for (field, value) in fields {
+        match field.as_str() {
+            "protocol" => {
+                profile.protocol = match value.as_str().map(str::to_ascii_uppercase).as_deref() {
+                    Some("TCP") => Some("TCP"),
+                    Some("UDP") => Some("UDP"),
+                    _ => return Err(invalid(field)),
+                }
+            }
+            "ports" => {
+                let spec = match value {
+                    Value::String(spec) => spec.clone(),
+                    Value::Array(items) => items.iter().map(|item| item.to_string()).collect::<Vec<_>>().join(","),
+                    _ => return Err(invalid(field)),
+                };
+                profile.ports = Some(parse_ports(&spec).map_err(|e| format!("profile '{}': {}", name, e))?);
+            }
+            "timeout" => profile.timeout = Some(Duration::from_secs(value.as_u64().filter(|secs| *secs > 0).ok_or_else(|| invalid(field))?)),
+            "chunk_size" => profile.chunk_size = Some(value.as_u64().filter(|size| *size > 0).ok_or_else(|| invalid(field))? as usize),
+            "min_banner_bytes" => profile.min_banner_bytes = Some(value.as_u64().ok_or_else(|| invalid(field))? as usize),
+            _ => return Err(format!("profile '{}': unknown setting '{}'", name, field)),
+        }
+    }
+    Ok(profile)
+}
+
+/// Reads a profiles file: a JSON object mapping profile names to their settings, e.g.
+/// `{"internal": {"protocol": "tcp", "ports": "1-1024", "timeout": 5, "chunk_size": 200}}`.
+pub fn load_profiles(path: &str) -> Result<HashMap<String, Profile>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let entries: HashMap<String, Value> = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?;
+
+    entries
+        .iter()
+        .map(|(name, value)| Ok((name.clone(), parse_profile(name, value).map_err(|e| format!("{}: {}", path, e))?)))
+        .collect()
+}
+
+/// Looks the profile up in the profiles file first, so custom profiles can replace built-in ones.
+pub fn resolve(name: &str, profiles_file: Option<&str>) -> Result<Profile, String> {
+    if let Some(path) = profiles_file {
+        if let Some(profile) = load_profiles(path)?.remove(name) {
+            return Ok(profile);
+        }
+    }
+
+    builtin(name).ok_or_else(|| format!("unknown profile '{}' (built-in: {})", name, BUILTIN_PROFILES.join(", ")))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+    use super::*;
+
+    fn profiles_file(content: &str) -> tempfile::NamedTempFile {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        file
+    }
+
+    #[test]
+    fn parse_ports_accepts_single_ports_lists_and_ranges() {
+        assert_eq!(parse_ports("80"), Ok(vec![80]));
+        assert_eq!(parse_ports("22,80,443"), Ok(vec![22, 80, 443]));
+        assert_eq!(parse_ports("8000-8003"), Ok(vec![8000, 8001, 8002, 8003]));
+        assert_eq!(parse_ports(" 443, 20-22 ,22,80,"), Ok(vec![20, 21, 22, 80, 443]));
+        assert_eq!(parse_ports("65535"), Ok(vec![65535]));
+        assert_eq!(parse_ports("1-65535").map(|ports| ports.len()), Ok(65535));
+    }
+
+    #[test]
+    fn parse_ports_rejects_malformed_specs() {
+        for spec in ["", ",", "http", "80-", "-80", "90-80", "65536", "1-65536", "80;443", "8o"] {
+            assert!(parse_ports(spec).is_err(), "{:?}", spec);
+        }
+    }
+
+    #[test]
+    fn builtin_profiles_resolve_by_name() {
+        for name in BUILTIN_PROFILES {
+            let profile = resolve(name, None).unwrap();
+            assert!(profile.ports.is_some_and(|ports| !ports.is_empty()), "{}", name);
+        }
+        assert_eq!(resolve("web", None).unwrap().timeout, Some(Duration::from_secs(10)));
+        assert!(resolve("stealth", None).unwrap_err().contains("quick, web, db"));
+    }
+
+    #[test]
+    fn profiles_file_replaces_builtins_and_adds_profiles() {
+        let file = profiles_file(r#"{"web": {"ports": [8080, 80]}, "internal": {"protocol": "udp", "ports": "161,53", "timeout": 5, "chunk_size": 200}}"#);
+        let path = file.path().to_str();
+
+        // The custom web profile replaces the built-in one entirely, unset fields stay unset
+        let web = resolve("web", path).unwrap();
+        assert_eq!(web.ports, Some(vec![80, 8080]));
+        assert_eq!(web.timeout, None);
+
+        let internal = resolve("internal", path).unwrap();
+        assert_eq!(internal.protocol, Some("UDP"));
+        assert_eq!(internal.ports, Some(vec![53, 161]));
+        assert_eq!(internal.timeout, Some(Duration::from_secs(5)));
+        assert_eq!(internal.chunk_size, Some(200));
+
+        // Names the file does not define still fall back to the built-ins
+        assert_eq!(resolve("db", path).unwrap().ports, builtin("db").unwrap().ports);
+    }
+
+    #[test]
+    fn invalid_profiles_files_are_errors() {
+        for content in [
+            "not json",
+            r#"{"x": 5}"#,
+            r#"{"x": {"protocol": "icmp"}}"#,
+            r#"{"x": {"ports": "90-80"}}"#,
+            r#"{"x": {"timeout": 0}}"#,
+            r#"{"x": {"speed": "fast"}}"#,
+        ] {
+            let file = profiles_file(content);
+            assert!(resolve("x", file.path().to_str()).is_err(), "{}", content);
+        }
+        assert!(resolve("web", Some("/nonexistent/profiles.json")).is_err());
+    }
+
+    #[test]
+    fn port_sets_match_their_documented_ranges() {
+        assert_eq!(port_set("fast").unwrap().len(), 16);
+        assert_eq!(port_set(DEFAULT_PORT_SET), Some((1..=1024).collect()));
+        assert_eq!(port_set("full").unwrap().len(), 65535);
+        assert_eq!(port_set("huge"), None);
+    }
+}
@@ -0,0 +1,204 @@
+use std::fmt;
+use tokio::net::UdpSocket;
+use tokio::time::Instant;
+use crate::scanning::protocols::{ProbeMatch, ProbeTarget};
+
+// Wildcard device instance, answered by most devices when the real instance is unknown
+const WILDCARD_INSTANCE: u32 = 0x3F_FFFF;
+const OBJECT_TYPE_DEVICE: u32 = 8;
+
+const PROP_FIRMWARE_REVISION: u8 = 44;
+const PROP_MODEL_NAME: u8 = 70;
+const PROP_OBJECT_IDENTIFIER: u8 = 75;
+const PROP_VENDOR_IDENTIFIER: u8 = 120;
+const PROP_VENDOR_NAME: u8 = 121;
+
+#[derive(Debug, Clone)]
+pub struct BacnetServiceInfo {
+    pub device_id: u32,
+    pub vendor_id: Option<u32>,
+    pub vendor_name: Option<String>,
+    pub model_name: Option<String>,
+    pub firmware_revision: Option<String>,
+}
+
+impl fmt::Display for BacnetServiceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "BACnet device {}", self.device_id)?;
+        if let Some(vendor_id) = self.vendor_id {
+            write!(f, " (vendor id: {})", vendor_id)?;
+        }
+        for (label, value) in [
+            ("vendor", &self.vendor_name),
+            ("model", &self.model_name),
+            ("firmware", &self.firmware_revision),
+        ] {
+            if let Some(value) = value {
+                write!(f, ", {}: {}", label, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Value {
+    Unsigned(u32),
+    ObjectId(u32),
+    Text(String),
+    Other,
+}
+
+fn bvlc(npdu_apdu: &[u8]) -> Vec<u8> {
+    let len = (npdu_apdu.len() + 4) as u16;
+    let mut packet = vec![0x81, 0x0A];
+    packet.extend_from_slice(&len.to_be_bytes());
+    packet.extend_from_slice(npdu_apdu);
+    packet
+}
+
+fn who_is() -> Vec<u8> {
+    bvlc(&[0x01, 0x00, 0x10, 0x08])
+}
+
+fn read_property(invoke_id: u8, instance: u32, property: u8) -> Vec<u8> {
+    let object_id = (OBJECT_TYPE_DEVICE << 22) | instance;
+    let mut apdu = vec![0x01, 0x04, 0x00, 0x05, invoke_id, 0x0C, 0x0C];
+    apdu.extend_from_slice(&object_id.to_be_bytes());
+    apdu.extend_from_slice(&[0x19, property]);
+    bvlc(&apdu)
+}
+
+/// Strips the BVLC and NPDU headers and returns the APDU.
+fn apdu(packet: &[u8]) -> Option<&[u8]> {
+    if packet.len() < 6 || packet[0] != 0x81 {
+        return None;
+    }
+    // Forwarded-NPDU carries the original sender's address
+    let mut pos = if packet[1] == 0x04 { 10 } else { 4 };
+
+    let control = *packet.get(pos + 1)?;
+    if control & 0x80 != 0 {
+        return None;
+    }
+    pos += 2;
+    if control & 0x20 != 0 {
+        pos += 3 + *packet.get(pos + 2)? as usize;
+    }
+    if control & 0x08 != 0 {
+        pos += 3 + *packet.get(pos + 2)? as usize;
+    }
+    if control & 0x20 != 0 {
+        pos += 1;
+    }
+    packet.get(pos..)
+}
+
+/// Reads one application tagged value, returning it and the offset after it.
+fn read_application_value(data: &[u8], pos: usize) -> Option<(Value, usize)> {
+    let tag = *data.get(pos)?;
+    let mut start = pos + 1;
+    let mut len = (tag & 0x07) as usize;
+    if len == 5 {
+        len = *data.get(start)? as usize;
+        start += 1;
+    }
+    let bytes = data.get(start..start + len)?;
+    let as_u32 = || bytes.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32);
+
+    let value = match tag >> 4 {
+        2 => Value::Unsigned(as_u32()),
+        7 if !bytes.is_empty() => Value::Text(String::from_utf8_lossy(&bytes[1..]).trim().to_string()),
+        12 => Value::ObjectId(as_u32()),
+        _ => Value::Other,
+    };
+    Some((value, start + len))
+}
+
+fn parse_i_am(apdu: &[u8]) -> Option<(u32, Option<u32>)> {
+    if apdu.get(..2)? != [0x10, 0x00] {
+        return None;
+    }
+
+    let mut values = Vec::new();
+    let mut pos = 2;
+    while pos < apdu.len() {
+        let (value, next) = read_application_value(apdu, pos)?;
+        values.push(value);
+        pos = next;
+    }
+
+    let device_id = match values.first()? {
+        Value::ObjectId(id) => id & 0x3F_FFFF,
+        _ => return None,
+    };
+    let vendor_id = match values.get(3) {
+        Some(Value::Unsigned(id)) => Some(*id),
+        _ => None,
+    };
+    Some((device_id, vendor_id))
+}
+
+fn parse_read_property_ack(apdu: &[u8], invoke_id: u8) -> Option<Value> {
+    if apdu.len() < 3 || apdu[0] != 0x30 || apdu[1] != invoke_id || apdu[2] != 0x0C {
+        return None;
+    }
+    let opening = apdu.iter().position(|b| *b == 0x3E)?;
+    read_application_value(apdu, opening + 1).map(|(value, _)| value)
+}
+
+async fn exchange<T>(socket: &UdpSocket, target: &ProbeTarget<'_>, request: &[u8], parse: impl Fn(&[u8]) -> Option<T>) -> Option<T> {
+    socket.send_to(request, target.addr).await.ok()?;
+
+    let deadline = Instant::now() + target.timeout;
+    let mut buffer = [0u8; 1500];
+    loop {
+        let (n, _) = tokio::time::timeout_at(deadline, socket.recv_from(&mut buffer)).await.ok()?.ok()?;
+        if let Some(parsed) = apdu(&buffer[..n]).and_then(&parse) {
+            return Some(parsed);
+        }
+    }
+}
+
+async fn read_text(socket: &UdpSocket, target: &ProbeTarget<'_>, instance: u32, property: u8) -> Option<String> {
+    let request = read_property(property, instance, property);
+    match exchange(socket, target, &request, |apdu| parse_read_property_ack(apdu, property)).await? {
+        Value::Text(text) => Some(text),
+        _ => None,
+    }
+}
+
+/// Who-Is for the device instance, falling back to a wildcard ReadProperty for devices
+/// that answer Who-Is with a broadcast, then reads the descriptive device properties.
+pub(crate) async fn probe(socket: &UdpSocket, target: &ProbeTarget<'_>) -> Option<ProbeMatch> {
+    let (device_id, mut vendor_id) = match exchange(socket, target, &who_is(), parse_i_am).await {
+        Some(i_am) => i_am,
+        None => {
+            let request = read_property(PROP_OBJECT_IDENTIFIER, WILDCARD_INSTANCE, PROP_OBJECT_IDENTIFIER);
+            match exchange(socket, target, &request, |apdu| parse_read_property_ack(apdu, PROP_OBJECT_IDENTIFIER)).await? {
+                Value::ObjectId(id) => (id & 0x3F_FFFF, None),
+                _ => return None,
+            }
+        }
+    };
+
+    if vendor_id.is_none() {
+        let request = read_property(PROP_VENDOR_IDENTIFIER, device_id, PROP_VENDOR_IDENTIFIER);
+        if let Some(Value::Unsigned(id)) = exchange(socket, target, &request, |apdu| parse_read_property_ack(apdu, PROP_VENDOR_IDENTIFIER)).await {
+            vendor_id = Some(id);
+        }
+    }
+
+    let info = BacnetServiceInfo {
+        device_id,
+        vendor_id,
+        vendor_name: read_text(socket, target, device_id, PROP_VENDOR_NAME).await,
+        model_name: read_text(socket, target, device_id, PROP_MODEL_NAME).await,
+        firmware_revision: read_text(socket, target, device_id, PROP_FIRMWARE_REVISION).await,
+    };
+
+    Some(ProbeMatch {
+        banner: info.to_string(),
+        service: "bacnet BACnet/IP".to_string(),
+        findings: Vec::new(),
+    })
+}
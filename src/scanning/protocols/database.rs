@@ -0,0 +1,455 @@
+use std::fmt;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::scanning::protocols::{Finding, Severity};
+use crate::scanning::scanner::socket_addr;
+use crate::scanning::types::ScanResult;
+
+// PostgreSQL SSLRequest, answered with a single 'S' or 'N'
+const POSTGRES_SSL_REQUEST: [u8; 8] = [0x00, 0x00, 0x00, 0x08, 0x04, 0xD2, 0x16, 0x2F];
+const MONGODB_OP_MSG: i32 = 2013;
+/// Largest message a MongoDB server sends (`maxMessageSizeBytes`)
+const MONGODB_MAX_MESSAGE_BYTES: usize = 48_000_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseType {
+    Mysql,
+    Postgres,
+    Redis,
+    Mongodb,
+}
+
+impl DatabaseType {
+    pub const SUPPORTED: &'static str = "mysql, postgres, redis, mongodb, all";
+    pub const ALL: [DatabaseType; 4] = [DatabaseType::Mysql, DatabaseType::Postgres, DatabaseType::Redis, DatabaseType::Mongodb];
+
+    /// Parses a type name, or `all` for every supported type.
+    pub fn parse(name: &str) -> Result<Vec<Self>, String> {
+        match name.to_lowercase().as_str() {
+            "mysql" => Ok(vec![DatabaseType::Mysql]),
+            "postgres" | "postgresql" => Ok(vec![DatabaseType::Postgres]),
+            "redis" => Ok(vec![DatabaseType::Redis]),
+            "mongodb" | "mongo" => Ok(vec![DatabaseType::Mongodb]),
+            "all" => Ok(DatabaseType::ALL.to_vec()),
+            _ => Err(format!("unknown database type '{}' (supported: {})", name, Self::SUPPORTED)),
+        }
+    }
+
+    pub fn default_port(&self) -> u16 {
+        match self {
+            DatabaseType::Mysql => 3306,
+            DatabaseType::Postgres => 5432,
+            DatabaseType::Redis => 6379,
+            DatabaseType::Mongodb => 27017,
+        }
+    }
+
+    fn product(&self) -> &'static str {
+        match self {
+            DatabaseType::Mysql => "MySQL",
+            DatabaseType::Postgres => "PostgreSQL",
+            DatabaseType::Redis => "Redis",
+            DatabaseType::Mongodb => "MongoDB",
+        }
+    }
+
+    fn service(&self) -> &'static str {
+        match self {
+            DatabaseType::Mysql => "mysql",
+            DatabaseType::Postgres => "postgresql",
+            DatabaseType::Redis => "redis",
+            DatabaseType::Mongodb => "mongodb",
+        }
+    }
+}
+
+/// Product and version a database handshake revealed.
+#[derive(Debug, Clone)]
+pub struct ServiceFingerprint {
+    pub product: &'static str,
+    pub version: Option<String>,
+    /// Protocol details worth showing next to the version, e.g. whether TLS is offered
+    pub details: Vec<String>,
+}
+
+
+impl fmt::Display for ServiceFingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.product)?;
+        if let Some(version) = &self.version {
+            write!(f, " {}", version)?;
+        }
+        if !self.details.is_empty() {
+            write!(f, " ({})", self.details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// What one database handshake found out.
+#[derive(Debug, Clone)]
+pub struct DatabaseProbeResult {
+    pub db_type: DatabaseType,
+    pub fingerprint: ServiceFingerprint,
+    /// `None` when the handshake does not tell without trying credentials
+    pub auth_required: Option<bool>,
+    /// The first answer of the server, as received
+    pub banner: String,
+    pub findings: Vec<Finding>,
+}
+
+impl DatabaseProbeResult {
+    fn new(db_type: DatabaseType, banner: &[u8]) -> Self {
+        DatabaseProbeResult {
+            db_type,
+            fingerprint: ServiceFingerprint { product: db_type.product(), version: None, details: Vec::new() },
+            auth_required: None,
+            banner: String::from_utf8_lossy(banner).to_string(),
+            findings: Vec::new(),
+        }
+    }
+
+    fn set_auth_required(&mut self, auth_required: bool) {
+        self.auth_required = Some(auth_required);
+        match auth_required {
+            true => self.fingerprint.details.push("authentication required".to_string()),
+            false => {
+                self.fingerprint.details.push("no authentication".to_string());
+                let message = format!("{} accepts commands without authentication", self.fingerprint.product);
+                self.findings.push(Finding::new(Severity::High, message));
+            }
+        }
+    }
+
+    pub fn into_scan_result(self, port: u16) -> ScanResult {
+        let service = format!("{} {}", self.db_type.service(), self.fingerprint);
+        ScanResult::new(port, "TCP", self.banner, service).with_findings(self.findings)
+    }
+}
+
+async fn connect(addr: &str, timeout: Duration) -> Result<TcpStream, String> {
+    match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(stream)) => Ok(stream),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err("connection timed out".to_string()),
+    }
+}
+
+async fn read_some(stream: &mut TcpStream, timeout: Duration) -> Option<Vec<u8>> {
+    let mut buffer = vec![0u8; 4096];
+    let n = tokio::time::timeout(timeout, stream.read(&mut buffer)).await.ok()?.ok()?;
+    if n == 0 {
+        return None;
+    }
+    buffer.truncate(n);
+    Some(buffer)
+}
+
+/// `PING` answers `+PONG` without credentials, `-NOAUTH` with them and `-DENIED` in protected
+/// mode. An open server also answers `INFO server`, which carries the version.
+pub async fn probe_redis(addr: &str, timeout: Duration) -> Result<Option<DatabaseProbeResult>, String> {
+    let mut stream = connect(addr, timeout).await?;
+    if stream.write_all(b"PING\r\n").await.is_err() {
+        return Ok(None);
+    }
+    let Some(reply) = read_some(&mut stream, timeout).await else {
+        return Ok(None);
+    };
+
+    let mut result = DatabaseProbeResult::new(DatabaseType::Redis, &reply);
+    if reply.starts_with(b"+PONG") {
+        result.set_auth_required(false);
+        if stream.write_all(b"INFO server\r\n").await.is_ok() {
+            if let Some(info) = read_some(&mut stream, timeout).await {
+                result.fingerprint.version = String::from_utf8_lossy(&info)
+                    .lines()
+                    .find_map(|line| line.strip_prefix("redis_version:"))
+                    .map(|version| version.trim().to_string());
+            }
+        }
+    } else if reply.starts_with(b"-NOAUTH") || reply.starts_with(b"-ERR") {
+        result.set_auth_required(true);
+    } else if reply.starts_with(b"-DENIED") {
+        result.fingerprint.details.push("protected mode".to_string());
+    } else {
+        return Ok(None);
+    }
+    Ok(Some(result))
+}
+
+/// The server sends its handshake packet first: a 3 byte length, a sequence id, protocol
+/// version 10 and the NUL terminated server version. A 0xFF packet is an error, usually
+/// "Host is not allowed to connect". Whether a login is needed is not known until one is tried.
+pub async fn probe_mysql(addr: &str, timeout: Duration) -> Result<Option<DatabaseProbeResult>, String> {
+    let mut stream = connect(addr, timeout).await?;
+    let Some(packet) = read_some(&mut stream, timeout).await else {
+        return Ok(None);
+    };
+    let Some(payload) = packet.get(4..) else {
+        return Ok(None);
+    };
+
+    let mut result = DatabaseProbeResult::new(DatabaseType::Mysql, &packet);
+    match payload.first() {
+        Some(10) => {
+            let Some(end) = payload[1..].iter().position(|&byte| byte == 0) else {
+                return Ok(None);
+            };
+            let version = String::from_utf8_lossy(&payload[1..1 + end]).to_string();
+            if version.to_lowercase().contains("mariadb") {
+                result.fingerprint.product = "MariaDB";
+            }
+            result.fingerprint.version = Some(version);
+        }
+        Some(0xFF) => {
+            // Error code (2 bytes) followed by the message
+            let message = String::from_utf8_lossy(payload.get(3..).unwrap_or_default()).trim_start_matches('#').to_string();
+            result.fingerprint.details.push(message);
+        }
+        _ => return Ok(None),
+    }
+    Ok(Some(result))
+}
+
+/// StartupMessage (protocol 3.0) for the `postgres` user and database.
+fn postgres_startup() -> Vec<u8> {
+    let mut body = 196608i32.to_be_bytes().to_vec();
+    body.extend_from_slice(b"user\0postgres\0database\0postgres\0\0");
+    [&((body.len() + 4) as i32).to_be_bytes()[..], &body].concat()
+}
+
+/// PostgreSQL does not reveal its version before authentication. An SSLRequest shows whether
+/// TLS is offered, and a startup message on a second connection shows whether the `postgres`
+/// user gets in without a password (AuthenticationOk) or is asked for one.
+pub async fn probe_postgres(addr: &str, timeout: Duration) -> Result<Option<DatabaseProbeResult>, String> {
+    let mut stream = connect(addr, timeout).await?;
+    if stream.write_all(&POSTGRES_SSL_REQUEST).await.is_err() {
+        return Ok(None);
+    }
+    let Some(reply) = read_some(&mut stream, timeout).await else {
+        return Ok(None);
+    };
+
+    let mut result = DatabaseProbeResult::new(DatabaseType::Postgres, &reply);
+    match reply.as_slice() {
+        [b'S'] => result.fingerprint.details.push("TLS supported".to_string()),
+        [b'N'] => {
+            result.fingerprint.details.push("TLS not offered".to_string());
+            result.findings.push(Finding::new(Severity::Medium, "PostgreSQL does not offer TLS, credentials are sent in clear text"));
+        }
+        _ => return Ok(None),
+    }
+
+    let mut stream = connect(addr, timeout).await?;
+    if stream.write_all(&postgres_startup()).await.is_ok() {
+        match read_some(&mut stream, timeout).await.as_deref() {
+            // Authentication request 0 is AuthenticationOk
+            Some([b'R', rest @ ..]) => result.set_auth_required(i32_be(rest, 4) != Some(0)),
+            Some([b'E', ..]) => result.set_auth_required(true),
+            _ => {}
+        }
+    }
+    Ok(Some(result))
+}
+
+fn bson_cstring(data: &[u8]) -> Option<(&[u8], usize)> {
+    let end = data.iter().position(|&byte| byte == 0)?;
+    Some((&data[..end], end + 1))
+}
+
+fn i32_le(data: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn i32_be(data: &[u8], offset: usize) -> Option<i32> {
+    Some(i32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// Type and raw value of a top level field of a BSON document. Only the element types
+/// `buildInfo` and `listDatabases` replies use are understood; the walk stops at anything else.
+fn bson_field<'a>(document: &'a [u8], wanted: &str) -> Option<(u8, &'a [u8])> {
+    let mut pos = 4;
+    while let Some(&element_type) = document.get(pos) {
+        if element_type == 0 {
+            break;
+        }
+        let (name, name_len) = bson_cstring(document.get(pos + 1..)?)?;
+        let value = pos + 1 + name_len;
+        // Lengths come from the peer, so a negative or huge one ends the walk instead of overflowing
+        let length_at = |offset: usize| usize::try_from(i32_le(document, value)?).ok()?.checked_add(offset);
+        let len = match element_type {
+            0x01 | 0x09 | 0x11 | 0x12 => 8,
+            0x02 => length_at(4)?,
+            0x03 | 0x04 => length_at(0)?,
+            0x05 => length_at(5)?,
+            0x07 => 12,
+            0x08 => 1,
+            0x0A => 0,
+            0x10 => 4,
+            _ => return None,
+        };
+        let end = value.checked_add(len)?;
+        if name == wanted.as_bytes() {
+            return Some((element_type, document.get(value..end)?));
+        }
+        pos = end;
+    }
+    None
+}
+
+fn bson_string(document: &[u8], wanted: &str) -> Option<String> {
+    match bson_field(document, wanted)? {
+        (0x02, value) => {
+            let string = value.get(4..)?;
+            Some(String::from_utf8_lossy(string.strip_suffix(&[0]).unwrap_or(string)).to_string())
+        }
+        _ => None,
+    }
+}
+
+/// `ok` is a double in most replies and an int32 in some older servers.
+fn bson_ok(document: &[u8]) -> bool {
+    match bson_field(document, "ok") {
+        Some((0x01, value)) => value.try_into().map(f64::from_le_bytes).is_ok_and(|ok| ok == 1.0),
+        Some((0x10, value)) => value.try_into().map(i32::from_le_bytes).is_ok_and(|ok| ok == 1),
+        _ => false,
+    }
+}
+
+/// OP_MSG running `{<command>: 1, $db: "admin"}`.
+fn mongodb_command(command: &str) -> Vec<u8> {
+    let mut document = Vec::new();
+    document.push(0x10);
+    document.extend_from_slice(command.as_bytes());
+    document.push(0x00);
+    document.extend_from_slice(&1i32.to_le_bytes());
+    document.push(0x02);
+    document.extend_from_slice(b"$db\0");
+    document.extend_from_slice(&6i32.to_le_bytes());
+    document.extend_from_slice(b"admin\0");
+    document.push(0x00);
+    let document = [&((document.len() + 4) as i32).to_le_bytes()[..], &document].concat();
+
+    let mut body = Vec::new();
+    body.extend_from_slice(&1i32.to_le_bytes()); // request id
+    body.extend_from_slice(&0i32.to_le_bytes()); // response to
+    body.extend_from_slice(&MONGODB_OP_MSG.to_le_bytes());
+    body.extend_from_slice(&0u32.to_le_bytes()); // flag bits
+    body.push(0x00); // section kind 0, the body document
+    body.extend_from_slice(&document);
+    [&((body.len() + 4) as i32).to_le_bytes()[..], &body].concat()
+}
+
+/// Runs a command and returns the reply document.
+async fn mongodb_run(stream: &mut TcpStream, command: &str, timeout: Duration) -> Option<Vec<u8>> {
+    stream.write_all(&mongodb_command(command)).await.ok()?;
+
+    let mut reply = read_some(stream, timeout).await?;
+    let total = usize::try_from(i32_le(&reply, 0)?).ok().filter(|&total| total <= MONGODB_MAX_MESSAGE_BYTES)?;
+    while reply.len() < total {
+        reply.extend(read_some(stream, timeout).await?);
+    }
+    if i32_le(&reply, 12)? != MONGODB_OP_MSG {
+        return None;
+    }
+    // Header (16), flag bits (4) and section kind (1) come before the document
+    reply.get(21..).map(<[u8]>::to_vec)
+}
+
+/// `buildInfo` answers without credentials and carries the version. `listDatabases` only
+/// succeeds when authentication is off.
+pub async fn probe_mongodb(addr: &str, timeout: Duration) -> Result<Option<DatabaseProbeResult>, String> {
+    let mut stream = connect(addr, timeout).await?;
+    let Some(build_info) = mongodb_run(&mut stream, "buildInfo", timeout).await else {
+        return Ok(None);
+    };
+
+    let mut result = DatabaseProbeResult::new(DatabaseType::Mongodb, &build_info);
+    result.fingerprint.version = bson_string(&build_info, "version");
+    if let Some(databases) = mongodb_run(&mut stream, "listDatabases", timeout).await {
+        result.set_auth_required(!bson_ok(&databases));
+    }
+    Ok(Some(result))
+}
+
+/// Runs the handshake of the given database against the host, for the `database` subcommand.
+/// `Ok(None)` when the port is open but does not speak that protocol.
+pub async fn check(db_type: DatabaseType, host: &str, port: u16, timeout: Duration) -> Result<Option<DatabaseProbeResult>, String> {
+    let addr = socket_addr(host, port);
+    match db_type {
+        DatabaseType::Mysql => probe_mysql(&addr, timeout).await,
+        DatabaseType::Postgres => probe_postgres(&addr, timeout).await,
+        DatabaseType::Redis => probe_redis(&addr, timeout).await,
+        DatabaseType::Mongodb => probe_mongodb(&addr, timeout).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+    use super::*;
+    use crate::scanning::testing::tcp_reply_server;
+
+    /// `{version: <version>, ok: 1.0}` with a string length the caller can corrupt.
+    fn build_info(version: &str, string_len: i32) -> Vec<u8> {
+        let mut elements = vec![0x02];
+        elements.extend_from_slice(b"version\0");
+        elements.extend_from_slice(&string_len.to_le_bytes());
+        elements.extend_from_slice(version.as_bytes());
+        elements.push(0);
+        elements.push(0x01);
+        elements.extend_from_slice(b"ok\0");
+        elements.extend_from_slice(&1.0f64.to_le_bytes());
+        elements.push(0);
+        [&((elements.len() + 4) as i32).to_le_bytes()[..], &elements].concat()
+    }
+
+    fn op_msg(document: &[u8], total: i32) -> &'static [u8] {
+        let mut reply = Vec::new();
+        reply.extend_from_slice(&total.to_le_bytes());
+        reply.extend_from_slice(&0i32.to_le_bytes());
+        reply.extend_from_slice(&1i32.to_le_bytes());
+        reply.extend_from_slice(&MONGODB_OP_MSG.to_le_bytes());
+        reply.extend_from_slice(&0u32.to_le_bytes());
+        reply.push(0);
+        reply.extend_from_slice(document);
+        Box::leak(reply.into_boxed_slice())
+    }
+
+    #[test]
+    fn bson_fields_are_read_by_name() {
+        let document = build_info("7.0.4", 6);
+        assert_eq!(bson_string(&document, "version").as_deref(), Some("7.0.4"));
+        assert!(bson_ok(&document));
+        assert!(bson_field(&document, "missing").is_none());
+    }
+
+    #[test]
+    fn bson_lengths_from_the_peer_cannot_overflow() {
+        assert!(bson_field(&build_info("7.0.4", -1), "ok").is_none());
+        assert!(bson_field(&build_info("7.0.4", i32::MAX), "ok").is_none());
+        assert!(bson_string(&build_info("7.0.4", 100), "version").is_none());
+    }
+
+    #[tokio::test]
+    async fn mongodb_version_and_open_access() {
+        let document = build_info("7.0.4", 6);
+        let port = tcp_reply_server(op_msg(&document, (21 + document.len()) as i32)).await;
+
+        let result = probe_mongodb(&format!("127.0.0.1:{}", port), Duration::from_secs(2)).await.unwrap().unwrap();
+        assert_eq!(result.fingerprint.version.as_deref(), Some("7.0.4"));
+        assert_eq!(result.auth_required, Some(false));
+    }
+
+    #[tokio::test]
+    async fn mongodb_reply_above_the_message_limit_is_rejected() {
+        let port = tcp_reply_server(op_msg(&build_info("7.0.4", 6), i32::MAX)).await;
+
+        let started = Instant::now();
+        let result = probe_mongodb(&format!("127.0.0.1:{}", port), Duration::from_secs(5)).await.unwrap();
+        assert!(result.is_none());
+        // Rejected from the header, without waiting for 2 GiB that never come
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+use serde_json::Value;
+use crate::scanning::protocols::http::{self, HttpResponse};
+use crate::scanning::protocols::{Finding, ProbeTarget, Severity};
+
+// 2376 is the TLS variant of the engine API and is not reachable with plain HTTP
+pub(crate) const ENGINE_PORTS: &[u16] = &[2375];
+pub(crate) const REGISTRY_PORTS: &[u16] = &[5000];
+
+#[derive(Debug, Clone)]
+pub struct DockerEngineInfo {
+    pub version: String,
+    pub api_version: Option<String>,
+    pub os: Option<String>,
+    pub arch: Option<String>,
+}
+
+impl DockerEngineInfo {
+    pub(crate) fn findings(&self) -> Vec<Finding> {
+        vec![Finding::new(Severity::Critical, "Docker Engine API is exposed without authentication (root on the host)")]
+    }
+}
+
+impl fmt::Display for DockerEngineInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Docker Engine {}", self.version)?;
+        if let Some(api_version) = &self.api_version {
+            write!(f, " (API {})", api_version)?;
+        }
+        if let (Some(os), Some(arch)) = (&self.os, &self.arch) {
+            write!(f, " {}/{}", os, arch)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DockerRegistryInfo {
+    pub api_version: String,
+    pub auth_required: bool,
+    pub repositories: Vec<String>,
+}
+
+impl DockerRegistryInfo {
+    pub(crate) fn findings(&self) -> Vec<Finding> {
+        if self.auth_required {
+            Vec::new()
+        } else {
+            vec![Finding::new(Severity::High, "Docker registry allows anonymous access")]
+        }
+    }
+}
+
+impl fmt::Display for DockerRegistryInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Docker Registry ({})", self.api_version)?;
+        if self.auth_required {
+            write!(f, " authentication required")
+        } else if self.repositories.is_empty() {
+            write!(f, " anonymous access")
+        } else {
+            write!(f, " anonymous access, repositories: {}", self.repositories.join(" "))
+        }
+    }
+}
+
+async fn get(target: &ProbeTarget<'_>, path: &str) -> Option<HttpResponse> {
+    http::request(target, &http::build_request("GET", path, target.addr, &[])).await
+}
+
+fn json_string(json: &Value, key: &str) -> Option<String> {
+    json.get(key)?.as_str().map(str::to_string)
+}
+
+/// `GET /version` answers without credentials when the daemon listens on plain TCP.
+pub(crate) async fn detect_engine(target: &ProbeTarget<'_>) -> Option<DockerEngineInfo> {
+    let response = get(target, "/version").await.filter(|response| response.status == 200)?;
+    let json: Value = serde_json::from_str(&response.body).ok()?;
+
+    Some(DockerEngineInfo {
+        version: json_string(&json, "Version")?,
+        api_version: json_string(&json, "ApiVersion"),
+        os: json_string(&json, "Os"),
+        arch: json_string(&json, "Arch"),
+    })
+}
+
+/// `GET /v2/` identifies a registry by its API version header, 200 means anonymous access
+/// and the catalog is listed as well.
+pub(crate) async fn detect_registry(target: &ProbeTarget<'_>) -> Option<DockerRegistryInfo> {
+    let response = get(target, "/v2/").await?;
+    let api_version = response.header("Docker-Distribution-Api-Version")?.to_string();
+
+    let auth_required = response.status == 401;
+    let repositories = if auth_required {
+        Vec::new()
+    } else {
+        get(target, "/v2/_catalog")
+            .await
+            .filter(|response| response.status == 200)
+            .and_then(|response| serde_json::from_str::<Value>(&response.body).ok())
+            .and_then(|json| {
+                json.get("repositories")?
+                    .as_array()
+                    .map(|repositories| repositories.iter().filter_map(|name| name.as_str().map(str::to_string)).collect())
+            })
+            .unwrap_or_default()
+    };
+
+    Some(DockerRegistryInfo { api_version, auth_required, repositories })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+    use crate::scanning::testing::http_server;
+
+    async fn target_on(routes: &'static [(&'static str, &'static str)]) -> String {
+        format!("127.0.0.1:{}", http_server(routes).await)
+    }
+
+    fn target(addr: &str) -> ProbeTarget<'_> {
+        let port = addr.rsplit_once(':').unwrap().1.parse().unwrap();
+        ProbeTarget { addr, port, timeout: Duration::from_millis(500), proxy: None, user_agent: None }
+    }
+
+    #[tokio::test]
+    async fn engine_version_is_parsed_and_reported_as_critical() {
+        let addr = target_on(&[(
+            "/version",
+            "HTTP/1.0 200 OK\r\nContent-Type: application/json\r\n\r\n{\"Version\":\"24.0.7\",\"ApiVersion\":\"1.43\",\"Os\":\"linux\",\"Arch\":\"amd64\",\"GoVersion\":\"go1.20.10\"}",
+        )])
+        .await;
+        let info = detect_engine(&target(&addr)).await.unwrap();
+
+        assert_eq!(info.to_string(), "Docker Engine 24.0.7 (API 1.43) linux/amd64");
+        let findings = info.findings();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].severity, Severity::Critical);
+    }
+
+    #[tokio::test]
+    async fn engine_without_version_field_is_not_docker() {
+        let addr = target_on(&[("/version", "HTTP/1.0 200 OK\r\n\r\n{\"version\":\"1.2.3\"}")]).await;
+        assert!(detect_engine(&target(&addr)).await.is_none());
+
+        let addr = target_on(&[("/version", "HTTP/1.0 200 OK\r\n\r\nnot json")]).await;
+        assert!(detect_engine(&target(&addr)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn protected_engine_is_not_reported() {
+        let addr = target_on(&[("/version", "HTTP/1.0 401 Unauthorized\r\n\r\n{\"Version\":\"24.0.7\"}")]).await;
+        assert!(detect_engine(&target(&addr)).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn anonymous_registry_lists_repositories() {
+        let addr = target_on(&[
+            ("/v2/", "HTTP/1.0 200 OK\r\nDocker-Distribution-Api-Version: registry/2.0\r\n\r\n{}"),
+            ("/v2/_catalog", "HTTP/1.0 200 OK\r\n\r\n{\"repositories\":[\"app\",\"base/alpine\"]}"),
+        ])
+        .await;
+        let info = detect_registry(&target(&addr)).await.unwrap();
+
+        assert_eq!(info.to_string(), "Docker Registry (registry/2.0) anonymous access, repositories: app base/alpine");
+        assert_eq!(info.findings().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn registry_requiring_authentication_has_no_finding() {
+        let addr = target_on(&[("/v2/", "HTTP/1.0 401 Unauthorized\r\nDocker-Distribution-Api-Version: registry/2.0\r\n\r\n")]).await;
+        let info = detect_registry(&target(&addr)).await.unwrap();
+
+        assert!(info.auth_required);
+        assert!(info.findings().is_empty());
+    }
+}
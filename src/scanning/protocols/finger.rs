@@ -0,0 +1,165 @@
+use std::fmt;
+use std::time::Duration;
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
+use futures::future::BoxFuture;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::scanning::protocols::{Finding, ProbeMatch, ProbeResult, ServiceProbe, Severity};
+
+pub const FINGER_PORT: u16 = 79;
+
+// fingerd closes the connection after answering, but a user's plan can be long
+const MAX_RESPONSE: usize = 16 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct FingerUser {
+    pub login: String,
+    pub name: String,
+    pub last_login: Option<DateTime<Utc>>,
+    pub plan: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FingerServiceInfo {
+    pub responding: bool,
+    pub user_list: Vec<String>,
+    pub queried_user: Option<FingerUser>,
+}
+
+impl fmt::Display for FingerServiceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(user) = &self.queried_user {
+            write!(f, "Finger user {} ({})", user.login, user.name)?;
+            match user.last_login {
+                Some(last_login) => write!(f, ", last login {}", last_login.format("%Y-%m-%d %H:%M UTC"))?,
+                None => write!(f, ", never logged in")?,
+            }
+            if let Some(plan) = &user.plan {
+                write!(f, ", plan: {}", plan)?;
+            }
+            return Ok(());
+        }
+
+        match self.user_list.len() {
+            0 => write!(f, "Finger (no users logged on)"),
+            count => write!(f, "Finger ({} users logged on: {})", count, self.user_list.join(", ")),
+        }
+    }
+}
+
+/// Logins from the short listing fingerd sends for an empty query: a header line starting
+/// with "Login", then one line per session with the login first.
+fn parse_user_list(response: &str) -> Vec<String> {
+    let mut lines = response.lines().skip_while(|line| !line.trim_start().starts_with("Login"));
+    lines.next();
+
+    let mut users: Vec<String> = Vec::new();
+    for login in lines.filter_map(|line| line.split_whitespace().next()) {
+        if !users.iter().any(|user| user == login) {
+            users.push(login.to_string());
+        }
+    }
+    users
+}
+
+/// "Last login Wed Oct 15 10:01 2025 (UTC) on pts/0", or "On since Wed Oct 15 10:01 (UTC)"
+/// for a user still logged on. Recent logins omit the year, which is then the current one.
+fn parse_last_login(line: &str) -> Option<DateTime<Utc>> {
+    let rest = line.strip_prefix("Last login ").or_else(|| line.strip_prefix("On since "))?;
+    let fields: Vec<&str> = rest.split_whitespace().skip(1).take(4).collect();
+    let (month, day, time) = (fields.first()?, fields.get(1)?, fields.get(2)?);
+    let year = fields
+        .get(3)
+        .filter(|year| year.len() == 4 && year.chars().all(|c| c.is_ascii_digit()))
+        .map(|year| year.to_string())
+        .unwrap_or_else(|| Utc::now().year().to_string());
+
+    let parsed = NaiveDateTime::parse_from_str(&format!("{} {} {} {}", month, day, time, year), "%b %d %H:%M %Y").ok()?;
+    Some(parsed.and_utc())
+}
+
+/// The long form fingerd sends for a single user: "Login: x  Name: y", session lines and
+/// an optional "Plan:" section that runs to the end of the response.
+fn parse_user(response: &str) -> Option<FingerUser> {
+    let first = response.lines().find(|line| line.trim_start().starts_with("Login:"))?;
+    let (login_part, name_part) = first.split_once("Name:").unwrap_or((first, ""));
+    let mut user = FingerUser {
+        login: login_part.trim_start().trim_start_matches("Login:").trim().to_string(),
+        name: name_part.trim().to_string(),
+        last_login: None,
+        plan: None,
+    };
+
+    let mut lines = response.lines();
+    for line in lines.by_ref() {
+        let line = line.trim();
+        if line == "Plan:" {
+            break;
+        }
+        if user.last_login.is_none() {
+            user.last_login = parse_last_login(line);
+        }
+    }
+
+    let plan = lines.collect::<Vec<_>>().join(" ").split_whitespace().collect::<Vec<_>>().join(" ");
+    if !plan.is_empty() {
+        user.plan = Some(plan);
+    }
+    Some(user)
+}
+
+async fn read_response(stream: &mut TcpStream, timeout: Duration) -> Vec<u8> {
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 1024];
+    while response.len() < MAX_RESPONSE {
+        match tokio::time::timeout(timeout, stream.read(&mut buffer)).await {
+            Ok(Ok(n)) if n > 0 => response.extend_from_slice(&buffer[..n]),
+            _ => break,
+        }
+    }
+    response
+}
+
+/// Sends an empty query, which lists the logged on users, or a query for a single user.
+pub(crate) async fn probe(stream: &mut TcpStream, timeout: Duration, user: Option<&str>) -> Option<ProbeMatch> {
+    let query = format!("{}\r\n", user.unwrap_or_default());
+    stream.write_all(query.as_bytes()).await.ok()?;
+
+    let response = read_response(stream, timeout).await;
+    let response = String::from_utf8_lossy(&response);
+    let info = FingerServiceInfo {
+        responding: !response.trim().is_empty(),
+        user_list: parse_user_list(&response),
+        queried_user: user.and_then(|_| parse_user(&response)),
+    };
+    if !info.responding {
+        return None;
+    }
+
+    Some(ProbeMatch {
+        banner: info.to_string(),
+        service: "finger".to_string(),
+        findings: vec![Finding::new(Severity::Medium, "Finger service answers queries, user accounts can be enumerated")],
+    })
+}
+
+/// Replaces the built-in list query on port 79 with a query for one user (`--finger-user`).
+pub struct FingerUserProbe {
+    user: String,
+}
+
+impl FingerUserProbe {
+    pub fn new(user: String) -> Self {
+        FingerUserProbe { user }
+    }
+}
+
+impl ServiceProbe for FingerUserProbe {
+    fn matching_ports(&self) -> &[u16] {
+        &[FINGER_PORT]
+    }
+
+    fn probe(&self, mut stream: TcpStream, timeout: Duration) -> BoxFuture<'_, ProbeResult> {
+        Box::pin(async move { probe(&mut stream, timeout, Some(&self.user)).await })
+    }
+}
@@ -0,0 +1,266 @@
+use std::fmt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::scanning::protocols::{Finding, ProbeMatch, ProbeTarget, Severity};
+
+pub(crate) const GRPC_PORTS: &[u16] = &[50051];
+
+const H2_PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+// Empty SETTINGS frame: length 0, type 0x4, no flags, stream 0
+const EMPTY_SETTINGS: [u8; 9] = [0x00, 0x00, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00, 0x00];
+const FRAME_SETTINGS: u8 = 0x04;
+
+const REFLECTION_PATH: &str = "/grpc.reflection.v1alpha.ServerReflection/ServerReflectionInfo";
+
+// Field numbers from grpc/reflection/v1alpha/reflection.proto and descriptor.proto
+const REQUEST_FILE_CONTAINING_SYMBOL: u32 = 4;
+const REQUEST_LIST_SERVICES: u32 = 7;
+const RESPONSE_FILE_DESCRIPTOR: u32 = 4;
+const RESPONSE_LIST_SERVICES: u32 = 6;
+const FILE_PACKAGE: u32 = 2;
+const FILE_SERVICE: u32 = 6;
+const SERVICE_NAME: u32 = 1;
+const SERVICE_METHOD: u32 = 2;
+
+#[derive(Debug, Clone)]
+pub struct GrpcMethod {
+    pub name: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct GrpcService {
+    pub name: String,
+    pub methods: Vec<GrpcMethod>,
+}
+
+/// Services listed through server reflection. Empty when reflection is turned off.
+#[derive(Debug, Clone, Default)]
+pub struct GrpcServiceInfo {
+    pub services: Vec<GrpcService>,
+}
+
+impl GrpcServiceInfo {
+    pub(crate) fn findings(&self) -> Vec<Finding> {
+        if self.services.is_empty() {
+            return Vec::new();
+        }
+        vec![Finding::new(Severity::Medium, "gRPC server reflection is enabled, the whole API can be enumerated")]
+    }
+}
+
+impl fmt::Display for GrpcServiceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.services.is_empty() {
+            return write!(f, "gRPC (reflection not available)");
+        }
+
+        let services = self
+            .services
+            .iter()
+            .map(|service| {
+                let methods = service.methods.iter().map(|method| method.name.as_str()).collect::<Vec<_>>();
+                format!("{} [{}]", service.name, methods.join(", "))
+            })
+            .collect::<Vec<_>>();
+        write!(f, "gRPC (reflection: {})", services.join(", "))
+    }
+}
+
+fn encode_varint(mut value: u64, out: &mut Vec<u8>) {
+    while value >= 0x80 {
+        out.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+fn encode_string_field(field: u32, value: &str) -> Vec<u8> {
+    let mut out = Vec::new();
+    encode_varint(((field << 3) | 2) as u64, &mut out);
+    encode_varint(value.len() as u64, &mut out);
+    out.extend_from_slice(value.as_bytes());
+    out
+}
+
+fn decode_varint(data: &[u8], pos: &mut usize) -> Option<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7F) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// The length-delimited fields of a protobuf message with the given number, in order.
+/// Every message read here only needs strings and nested messages.
+fn bytes_fields(data: &[u8], wanted: u32) -> Vec<&[u8]> {
+    let mut fields = Vec::new();
+    let mut pos = 0;
+    while pos < data.len() {
+        let Some(key) = decode_varint(data, &mut pos) else {
+            break;
+        };
+        let skip = match key & 0x07 {
+            0 => decode_varint(data, &mut pos).map(|_| 0),
+            1 => Some(8),
+            2 => decode_varint(data, &mut pos).and_then(|len| usize::try_from(len).ok()),
+            5 => Some(4),
+            _ => None,
+        };
+        let Some(len) = skip else {
+            break;
+        };
+        let Some(end) = pos.checked_add(len) else {
+            break;
+        };
+        let Some(value) = data.get(pos..end) else {
+            break;
+        };
+        if key & 0x07 == 2 && (key >> 3) as u32 == wanted {
+            fields.push(value);
+        }
+        pos = end;
+    }
+    fields
+}
+
+fn string_field(data: &[u8], field: u32) -> Option<String> {
+    bytes_fields(data, field).first().map(|value| String::from_utf8_lossy(value).to_string())
+}
+
+fn grpc_frame(message: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0x00];
+    frame.extend_from_slice(&(message.len() as u32).to_be_bytes());
+    frame.extend_from_slice(message);
+    frame
+}
+
+/// Makes one ServerReflectionInfo call with a single request and returns the first response
+/// message. The request body ends after one message, which ends the stream for the server too.
+async fn reflect(client: &reqwest::Client, base_url: &str, request: Vec<u8>) -> Option<Vec<u8>> {
+    let response = client
+        .post(format!("{}{}", base_url, REFLECTION_PATH))
+        .header("content-type", "application/grpc")
+        .header("te", "trailers")
+        .body(grpc_frame(&request))
+        .send()
+        .await
+        .ok()?;
+
+    let body = response.bytes().await.ok()?;
+    let len = u32::from_be_bytes(body.get(1..5)?.try_into().ok()?) as usize;
+    body.get(5..5 + len).map(<[u8]>::to_vec)
+}
+
+async fn list_services(client: &reqwest::Client, base_url: &str) -> Vec<String> {
+    let Some(response) = reflect(client, base_url, encode_string_field(REQUEST_LIST_SERVICES, "")).await else {
+        return Vec::new();
+    };
+
+    bytes_fields(&response, RESPONSE_LIST_SERVICES)
+        .first()
+        .map(|list| bytes_fields(list, 1).iter().filter_map(|service| string_field(service, SERVICE_NAME)).collect())
+        .unwrap_or_default()
+}
+
+/// Reads the file descriptors that define the service and picks out its RPC method names.
+async fn service_methods(client: &reqwest::Client, base_url: &str, service: &str) -> Vec<GrpcMethod> {
+    let Some(response) = reflect(client, base_url, encode_string_field(REQUEST_FILE_CONTAINING_SYMBOL, service)).await else {
+        return Vec::new();
+    };
+
+    let mut methods = Vec::new();
+    for descriptors in bytes_fields(&response, RESPONSE_FILE_DESCRIPTOR) {
+        for file in bytes_fields(descriptors, 1) {
+            let package = string_field(file, FILE_PACKAGE).unwrap_or_default();
+            for definition in bytes_fields(file, FILE_SERVICE) {
+                let name = string_field(definition, SERVICE_NAME).unwrap_or_default();
+                let full_name = if package.is_empty() { name } else { format!("{}.{}", package, name) };
+                if full_name != service {
+                    continue;
+                }
+                methods.extend(
+                    bytes_fields(definition, SERVICE_METHOD)
+                        .iter()
+                        .filter_map(|method| string_field(method, 1))
+                        .map(|name| GrpcMethod { name }),
+                );
+            }
+        }
+    }
+    methods
+}
+
+/// Lists the services through server reflection over cleartext HTTP/2 (h2c).
+async fn enumerate(target: &ProbeTarget<'_>) -> GrpcServiceInfo {
+    let (host, port) = target.addr.rsplit_once(':').unwrap_or((target.addr, ""));
+    let base_url = match host.contains(':') {
+        true => format!("http://[{}]:{}", host, port),
+        false => format!("http://{}:{}", host, port),
+    };
+    let Ok(client) = reqwest::Client::builder().http2_prior_knowledge().timeout(target.timeout).build() else {
+        return GrpcServiceInfo::default();
+    };
+
+    let mut info = GrpcServiceInfo::default();
+    for name in list_services(&client, &base_url).await {
+        // The reflection service lists itself
+        if name.starts_with("grpc.reflection.") {
+            continue;
+        }
+        let methods = service_methods(&client, &base_url, &name).await;
+        info.services.push(GrpcService { name, methods });
+    }
+    info
+}
+
+/// Confirms the port speaks cleartext HTTP/2 by sending the connection preface, then
+/// enumerates the API. Reflection is skipped through a proxy, which the client cannot use.
+pub(crate) async fn probe(target: &ProbeTarget<'_>, stream: &mut TcpStream) -> Option<ProbeMatch> {
+    stream.write_all(&[H2_PREFACE, &EMPTY_SETTINGS].concat()).await.ok()?;
+
+    let mut header = [0u8; 9];
+    tokio::time::timeout(target.timeout, stream.read_exact(&mut header)).await.ok()?.ok()?;
+    if header[3] != FRAME_SETTINGS {
+        return None;
+    }
+
+    let info = match target.proxy {
+        Some(_) => GrpcServiceInfo::default(),
+        None => enumerate(target).await,
+    };
+
+    Some(ProbeMatch {
+        banner: format!("HTTP/2; {}", info),
+        service: "grpc".to_string(),
+        findings: info.findings(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bytes_fields_reads_matching_length_delimited_fields() {
+        // field 1 varint 150, field 2 "ab", field 3 "x", field 2 "cd"
+        let data = [0x08, 0x96, 0x01, 0x12, 2, b'a', b'b', 0x1A, 1, b'x', 0x12, 2, b'c', b'd'];
+        assert_eq!(bytes_fields(&data, 2), vec![&b"ab"[..], &b"cd"[..]]);
+        assert_eq!(string_field(&data, 3).as_deref(), Some("x"));
+    }
+
+    #[test]
+    fn bytes_fields_stops_at_lengths_past_the_end() {
+        // field 2 claims u64::MAX bytes, which must not overflow the position
+        let mut data = vec![0x12, 2, b'o', b'k', 0x12];
+        data.extend_from_slice(&[0xFF; 9]);
+        data.push(0x01);
+        assert_eq!(bytes_fields(&data, 2), vec![&b"ok"[..]]);
+
+        assert_eq!(bytes_fields(&[0x12, 5, b'a'], 2), Vec::<&[u8]>::new());
+    }
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use regex::Regex;
+use crate::scanning::protocols::{http, Finding, ProbeTarget, Severity};
+
+const STATS_PATH: &str = "/stats";
+
+#[derive(Debug, Clone)]
+pub struct HaProxyBackend {
+    pub name: String,
+    pub servers: usize,
+    pub current_sessions: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HaProxyInfo {
+    pub version: Option<String>,
+    pub backends: Vec<HaProxyBackend>,
+    pub auth_required: bool,
+}
+
+impl HaProxyInfo {
+    pub(crate) fn findings(&self) -> Vec<Finding> {
+        if self.auth_required {
+            Vec::new()
+        } else {
+            vec![Finding::new(Severity::Medium, "HAProxy statistics page is readable without authentication")]
+        }
+    }
+}
+
+impl fmt::Display for HaProxyInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "HAProxy")?;
+        if let Some(version) = &self.version {
+            write!(f, " {}", version)?;
+        }
+        if self.auth_required {
+            return write!(f, " stats (authentication required)");
+        }
+
+        let backends = self
+            .backends
+            .iter()
+            .map(|backend| match backend.current_sessions {
+                Some(sessions) => format!("{} [{} servers, {} sessions]", backend.name, backend.servers, sessions),
+                None => format!("{} [{} servers]", backend.name, backend.servers),
+            })
+            .collect::<Vec<_>>();
+        write!(f, " stats (backends: {})", backends.join(", "))
+    }
+}
+
+fn is_stats_page(body: &str) -> bool {
+    body.contains("Statistics Report for HAProxy") || body.contains("HAProxy Statistics Report")
+}
+
+fn parse_version(body: &str) -> Option<String> {
+    let version = Regex::new(r"HAProxy version ([0-9][0-9A-Za-z.\-+~]*)").unwrap();
+    version.captures(body).map(|cap| cap[1].to_string())
+}
+
+/// Parses the `;csv` export: one line per frontend, server and backend, where the
+/// BACKEND line carries the backend totals.
+fn parse_csv(body: &str) -> Vec<HaProxyBackend> {
+    let mut lines = body.lines();
+    let Some(header) = lines.next().and_then(|line| line.strip_prefix("# ")) else {
+        return Vec::new();
+    };
+    let columns: Vec<&str> = header.split(',').collect();
+    let column = |name: &str| columns.iter().position(|column| *column == name);
+    let (Some(pxname), Some(svname), Some(scur)) = (column("pxname"), column("svname"), column("scur")) else {
+        return Vec::new();
+    };
+
+    let mut backends: Vec<HaProxyBackend> = Vec::new();
+    let mut servers: Vec<(String, usize)> = Vec::new();
+    for line in lines {
+        let fields: Vec<&str> = line.split(',').collect();
+        let (Some(proxy), Some(server)) = (fields.get(pxname), fields.get(svname)) else {
+            continue;
+        };
+
+        match *server {
+            "FRONTEND" => {}
+            "BACKEND" => backends.push(HaProxyBackend {
+                name: proxy.to_string(),
+                servers: servers.iter().find(|(name, _)| name == proxy).map(|(_, count)| *count).unwrap_or(0),
+                current_sessions: fields.get(scur).and_then(|value| value.parse().ok()),
+            }),
+            _ => match servers.iter_mut().find(|(name, _)| name == proxy) {
+                Some((_, count)) => *count += 1,
+                None => servers.push((proxy.to_string(), 1)),
+            },
+        }
+    }
+    backends
+}
+
+/// Fallback for pages without the CSV export: servers are anchored as `<a name="backend/server">`.
+fn parse_html(body: &str) -> Vec<HaProxyBackend> {
+    let anchor = Regex::new(r#"<a name="([^"/]+)/([^"]+)">"#).unwrap();
+    let mut backends: Vec<HaProxyBackend> = Vec::new();
+
+    for cap in anchor.captures_iter(body) {
+        let (proxy, server) = (&cap[1], &cap[2]);
+        let backend = match backends.iter_mut().position(|backend| backend.name == proxy) {
+            Some(index) => &mut backends[index],
+            None => {
+                backends.push(HaProxyBackend { name: proxy.to_string(), servers: 0, current_sessions: None });
+                backends.last_mut().unwrap()
+            }
+        };
+        if server != "Frontend" && server != "Backend" {
+            backend.servers += 1;
+        }
+    }
+
+    // Frontends only have their own anchors and no servers
+    backends.retain(|backend| backend.servers > 0);
+    backends
+}
+
+/// Requests the stats page and, when it is an HAProxy report, its CSV export for the
+/// backend details. A 401 naming HAProxy in its realm still identifies the page.
+pub(crate) async fn detect(target: &ProbeTarget<'_>) -> Option<HaProxyInfo> {
+    let stats = http::request(target, &http::build_request("GET", STATS_PATH, target.addr, &[])).await?;
+
+    if stats.status == 401 {
+        let realm_names_haproxy = http::auth_challenges(&stats)
+            .iter()
+            .any(|challenge| challenge.realm.as_deref().is_some_and(|realm| realm.contains("HAProxy")));
+        return realm_names_haproxy.then_some(HaProxyInfo { version: None, backends: Vec::new(), auth_required: true });
+    }
+
+    if stats.status != 200 || !is_stats_page(&stats.body) {
+        return None;
+    }
+
+    let csv_path = format!("{};csv", STATS_PATH);
+    let mut backends = http::request(target, &http::build_request("GET", &csv_path, target.addr, &[]))
+        .await
+        .filter(|response| response.status == 200)
+        .map(|response| parse_csv(&response.body))
+        .unwrap_or_default();
+    if backends.is_empty() {
+        backends = parse_html(&stats.body);
+    }
+
+    Some(HaProxyInfo {
+        version: parse_version(&stats.body),
+        backends,
+        auth_required: false,
+    })
+}
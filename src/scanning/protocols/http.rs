@@ -0,0 +1,311 @@
+use std::fmt;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::scanning::protocols::{docker, haproxy, jupyter, ntlm, source_control, webdav, ProbeMatch, ProbeTarget};
+use crate::scanning::scanner::socket_addr;
+use crate::scanning::types::WebProbeResult;
+
+pub(crate) const HTTP_PORTS: &[u16] = &[80, 2375, 3000, 5000, 8000, 8008, 8080, 8081, 8404, 8888, 8889];
+/// Ports the `web` subcommand checks
+pub const WEB_PORTS: &[u16] = &[80, 443, 8080, 8443, 3000, 5000, 8000];
+
+// Enough for headers and the start of a page, probes never need whole documents
+const MAX_RESPONSE_BYTES: usize = 256 * 1024;
+
+pub(crate) struct HttpResponse {
+    pub(crate) status: u16,
+    pub(crate) status_line: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: String,
+}
+
+impl HttpResponse {
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum AuthMethod {
+    Basic,
+    Digest,
+    Bearer,
+    Negotiate,
+    Ntlm,
+    Other(String),
+}
+
+impl AuthMethod {
+    fn parse(scheme: &str) -> Self {
+        match scheme.to_ascii_lowercase().as_str() {
+            "basic" => AuthMethod::Basic,
+            "digest" => AuthMethod::Digest,
+            "bearer" => AuthMethod::Bearer,
+            "negotiate" => AuthMethod::Negotiate,
+            "ntlm" => AuthMethod::Ntlm,
+            _ => AuthMethod::Other(scheme.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for AuthMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuthMethod::Basic => write!(f, "Basic"),
+            AuthMethod::Digest => write!(f, "Digest"),
+            AuthMethod::Bearer => write!(f, "Bearer"),
+            AuthMethod::Negotiate => write!(f, "Negotiate"),
+            AuthMethod::Ntlm => write!(f, "NTLM"),
+            AuthMethod::Other(scheme) => write!(f, "{}", scheme),
+        }
+    }
+}
+
+/// One authentication challenge from a `WWW-Authenticate` header. Realms are free text
+/// chosen by the admin and often name the product or purpose ("Camera Web Interface").
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct HttpAuthInfo {
+    pub(crate) method: AuthMethod,
+    pub(crate) realm: Option<String>,
+}
+
+impl fmt::Display for HttpAuthInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.realm {
+            Some(realm) => write!(f, "{} realm=\"{}\"", self.method, realm),
+            None => write!(f, "{}", self.method),
+        }
+    }
+}
+
+/// Splits on commas that are not inside a quoted string.
+fn split_unquoted(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Parses the challenges of every `WWW-Authenticate` header. A header can carry several
+/// challenges, e.g. `Basic realm="a", Digest realm="b", nonce="..."`; a comma separated
+/// piece that starts with a bare token opens a new challenge, `key=value` pieces belong
+/// to the current one.
+pub(crate) fn auth_challenges(response: &HttpResponse) -> Vec<HttpAuthInfo> {
+    let mut challenges: Vec<HttpAuthInfo> = Vec::new();
+
+    let values = response
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("WWW-Authenticate"))
+        .map(|(_, value)| value.as_str());
+
+    for value in values {
+        for piece in split_unquoted(value).into_iter().map(str::trim).filter(|piece| !piece.is_empty()) {
+            let param = match piece.split_once(char::is_whitespace) {
+                Some((token, rest)) if !token.contains('=') => {
+                    challenges.push(HttpAuthInfo { method: AuthMethod::parse(token), realm: None });
+                    rest.trim()
+                }
+                None if !piece.contains('=') => {
+                    challenges.push(HttpAuthInfo { method: AuthMethod::parse(piece), realm: None });
+                    continue;
+                }
+                _ => piece,
+            };
+
+            if let (Some((key, realm)), Some(challenge)) = (param.split_once('='), challenges.last_mut()) {
+                if key.trim().eq_ignore_ascii_case("realm") {
+                    challenge.realm = Some(realm.trim().trim_matches('"').to_string());
+                }
+            }
+        }
+    }
+
+    challenges.dedup();
+    challenges
+}
+
+pub(crate) fn build_request(method: &str, path: &str, addr: &str, extra_headers: &[(&str, &str)]) -> String {
+    let host = addr.rsplit_once(':').map(|(host, _)| host).unwrap_or(addr);
+    let mut request = format!("{} {} HTTP/1.0\r\nHost: {}\r\n", method, path, host);
+    for (name, value) in extra_headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    request
+}
+
+pub(crate) fn parse_response(raw: &[u8]) -> Option<HttpResponse> {
+    let text = String::from_utf8_lossy(raw);
+    let (head, body) = text.split_once("\r\n\r\n").unwrap_or((&text, ""));
+    let mut lines = head.lines();
+
+    let status_line = lines.next()?.trim().to_string();
+    if !status_line.starts_with("HTTP/") {
+        return None;
+    }
+    let status = status_line.split_whitespace().nth(1)?.parse().ok()?;
+
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    Some(HttpResponse {
+        status,
+        status_line,
+        headers,
+        body: body.to_string(),
+    })
+}
+
+/// Sends a raw request on `stream` and reads until the server closes the connection.
+pub(crate) async fn send(stream: &mut TcpStream, request: &str, timeout: Duration) -> Option<HttpResponse> {
+    stream.write_all(request.as_bytes()).await.ok()?;
+
+    let mut response = Vec::new();
+    let mut buffer = [0u8; 8192];
+    while response.len() < MAX_RESPONSE_BYTES {
+        match tokio::time::timeout(timeout, stream.read(&mut buffer)).await {
+            Ok(Ok(n)) if n > 0 => response.extend_from_slice(&buffer[..n]),
+            _ => break,
+        }
+    }
+
+    parse_response(&response)
+}
+
+/// Opens a fresh connection for probes that need more than one request.
+pub(crate) async fn request(target: &ProbeTarget<'_>, request: &str) -> Option<HttpResponse> {
+    let mut stream = target.connect().await?;
+    send(&mut stream, request, target.timeout).await
+}
+
+pub(crate) async fn probe(target: &ProbeTarget<'_>, stream: &mut TcpStream) -> Option<ProbeMatch> {
+    let headers: Vec<(&str, &str)> = target.user_agent.iter().map(|agent| ("User-Agent", *agent)).collect();
+    let response = send(stream, &build_request("GET", "/", target.addr, &headers), target.timeout).await?;
+
+    let server = response.header("Server").map(str::to_string);
+    let mut banner = response.status_line.clone();
+    if let Some(server) = &server {
+        banner.push_str(&format!("; Server: {}", server));
+    }
+
+    let mut findings = Vec::new();
+
+    if response.status == 401 {
+        let challenges = auth_challenges(&response);
+        if !challenges.is_empty() {
+            let methods = challenges.iter().map(|challenge| challenge.to_string()).collect::<Vec<_>>();
+            banner.push_str(&format!("; Auth: {}", methods.join(", ")));
+        }
+
+        if let Some(ntlm) = ntlm::detect(target, &challenges).await {
+            banner.push_str(&format!("; {}", ntlm));
+            findings.extend(ntlm.findings());
+        }
+    }
+
+    if let Some(dav) = webdav::detect(target).await {
+        banner.push_str(&format!("; {}", dav));
+        findings.extend(dav.findings());
+    }
+
+    if let Some(haproxy) = haproxy::detect(target).await {
+        banner.push_str(&format!("; {}", haproxy));
+        findings.extend(haproxy.findings());
+    }
+
+    if let Some(jupyter) = jupyter::detect(target).await {
+        banner.push_str(&format!("; {}", jupyter));
+        findings.extend(jupyter.findings());
+    }
+
+    if docker::ENGINE_PORTS.contains(&target.port) {
+        if let Some(engine) = docker::detect_engine(target).await {
+            banner.push_str(&format!("; {}", engine));
+            findings.extend(engine.findings());
+        }
+    }
+
+    if docker::REGISTRY_PORTS.contains(&target.port) {
+        if let Some(registry) = docker::detect_registry(target).await {
+            banner.push_str(&format!("; {}", registry));
+            findings.extend(registry.findings());
+        }
+    }
+
+    if let Some(scm) = source_control::detect(target, &response).await {
+        banner.push_str(&format!("; {}", scm));
+        findings.extend(scm.findings());
+    }
+
+    Some(ProbeMatch {
+        banner,
+        service: match server {
+            Some(server) => format!("http {}", server),
+            None => "http".to_string(),
+        },
+        findings,
+    })
+}
+
+fn web_result(port: u16, tls: bool, status: u16, header: impl Fn(&str) -> Option<String>) -> WebProbeResult {
+    WebProbeResult {
+        port,
+        status,
+        server: header("Server"),
+        powered_by: header("X-Powered-By"),
+        content_type: header("Content-Type"),
+        redirect: (300..400).contains(&status).then(|| header("Location")).flatten(),
+        tls,
+    }
+}
+
+/// `GET /` over TLS. Certificates are not verified, the point is to see what answers.
+async fn get_tls(host_header: &str, addr: &str, port: u16, timeout: Duration) -> Option<WebProbeResult> {
+    let client = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .redirect(reqwest::redirect::Policy::none())
+        .resolve(host_header, addr.parse().ok()?)
+        .timeout(timeout)
+        .build()
+        .ok()?;
+    let response = client.get(format!("https://{}/", socket_addr(host_header, port))).send().await.ok()?;
+
+    let headers = response.headers();
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).map(str::to_string);
+    Some(web_result(port, true, response.status().as_u16(), header))
+}
+
+/// Connects to the port and sends a plain HTTP/1.1 `GET /`. When the port does not answer
+/// with HTTP, or only complains that it expects TLS, the request is repeated over TLS.
+pub async fn check_web(host_header: &str, ip: &str, port: u16, timeout: Duration) -> Option<WebProbeResult> {
+    let addr = socket_addr(ip, port);
+    let mut stream = tokio::time::timeout(timeout, TcpStream::connect(&addr)).await.ok()?.ok()?;
+
+    let request = format!("GET / HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n\r\n", host_header);
+    let plain = send(&mut stream, &request, timeout)
+        .await
+        .filter(|response| !(response.status == 400 && response.body.contains("HTTPS port")));
+
+    match plain {
+        Some(response) => Some(web_result(port, false, response.status, |name| response.header(name).map(str::to_string))),
+        None => get_tls(host_header, &addr, port, timeout).await,
+    }
+}
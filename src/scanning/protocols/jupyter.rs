@@ -0,0 +1,74 @@
+use std::fmt;
+use serde_json::Value;
+use crate::scanning::protocols::{http, Finding, ProbeTarget, Severity};
+
+#[derive(Debug, Clone)]
+pub struct JupyterServiceInfo {
+    pub version: String,
+    pub kernels: Vec<String>,
+    pub auth_required: bool,
+    pub token_protected: bool,
+}
+
+impl JupyterServiceInfo {
+    pub(crate) fn findings(&self) -> Vec<Finding> {
+        if self.auth_required {
+            Vec::new()
+        } else {
+            vec![Finding::new(Severity::Critical, "Jupyter API is accessible without authentication (code execution)")]
+        }
+    }
+}
+
+impl fmt::Display for JupyterServiceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Jupyter Server {}", self.version)?;
+        if self.token_protected {
+            write!(f, " (token required)")
+        } else if self.auth_required {
+            write!(f, " (authentication required)")
+        } else if self.kernels.is_empty() {
+            write!(f, " (no authentication, no running kernels)")
+        } else {
+            write!(f, " (no authentication, kernels: {})", self.kernels.join(" "))
+        }
+    }
+}
+
+async fn get_json(target: &ProbeTarget<'_>, path: &str) -> Option<(u16, Option<Value>)> {
+    let response = http::request(target, &http::build_request("GET", path, target.addr, &[])).await?;
+    Some((response.status, serde_json::from_str(&response.body).ok()))
+}
+
+/// `GET /api` answers `{"version": "..."}` even when authentication is on; listing the
+/// kernels only works without it.
+pub(crate) async fn detect(target: &ProbeTarget<'_>) -> Option<JupyterServiceInfo> {
+    let (_, api) = get_json(target, "/api").await?;
+    let version = api?.get("version")?.as_str()?.to_string();
+
+    let kernels = match get_json(target, "/api/kernels").await {
+        Some((200, Some(Value::Array(kernels)))) => Some(
+            kernels
+                .iter()
+                .map(|kernel| {
+                    let name = kernel.get("name").and_then(Value::as_str).unwrap_or("kernel");
+                    let state = kernel.get("execution_state").and_then(Value::as_str).unwrap_or("unknown");
+                    format!("{}[{}]", name, state)
+                })
+                .collect(),
+        ),
+        _ => None,
+    };
+
+    let token_protected = kernels.is_none()
+        && http::request(target, &http::build_request("GET", "/login", target.addr, &[]))
+            .await
+            .is_some_and(|login| login.body.to_lowercase().contains("token"));
+
+    Some(JupyterServiceInfo {
+        version,
+        auth_required: kernels.is_none(),
+        kernels: kernels.unwrap_or_default(),
+        token_protected,
+    })
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::scanning::protocols::ProbeMatch;
+
+// Any realm works, the KDC answers with a KRB-ERROR either way
+const PROBE_REALM: &str = "WEBSHOT.LOCAL";
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else if len <= 0xFF {
+        out.extend_from_slice(&[0x81, len as u8]);
+    } else {
+        out.extend_from_slice(&[0x82, (len >> 8) as u8, len as u8]);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+fn integer(value: u32) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take(3).take_while(|b| **b == 0).count();
+    let mut content = bytes[skip..].to_vec();
+    if content[0] & 0x80 != 0 {
+        content.insert(0, 0);
+    }
+    tlv(0x02, &content)
+}
+
+fn general_string(value: &str) -> Vec<u8> {
+    tlv(0x1B, value.as_bytes())
+}
+
+fn context(tag: u8, inner: &[u8]) -> Vec<u8> {
+    tlv(0xA0 | tag, inner)
+}
+
+fn sequence(parts: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x30, &parts.concat())
+}
+
+fn principal(name_type: u32, names: &[&str]) -> Vec<u8> {
+    let names: Vec<Vec<u8>> = names.iter().map(|name| general_string(name)).collect();
+    sequence(&[context(0, &integer(name_type)), context(1, &sequence(&names))])
+}
+
+/// Builds a minimal AS-REQ for krbtgt without pre-authentication data.
+fn build_as_req(nonce: u32) -> Vec<u8> {
+    let body = sequence(&[
+        context(0, &tlv(0x03, &[0x00, 0x40, 0x81, 0x00, 0x10])),
+        context(1, &principal(1, &["webshot"])),
+        context(2, &general_string(PROBE_REALM)),
+        context(3, &principal(2, &["krbtgt", PROBE_REALM])),
+        context(5, &tlv(0x18, b"20370913024805Z")),
+        context(7, &integer(nonce)),
+        context(8, &sequence(&[integer(18), integer(17), integer(23)])),
+    ]);
+
+    let request = tlv(0x6A, &sequence(&[
+        context(1, &integer(5)),
+        context(2, &integer(10)),
+        context(4, &body),
+    ]));
+
+    let mut framed = (request.len() as u32).to_be_bytes().to_vec();
+    framed.extend_from_slice(&request);
+    framed
+}
+
+/// Reads a DER header and returns (tag, content start, content length).
+fn read_header(buf: &[u8], pos: usize) -> Option<(u8, usize, usize)> {
+    let tag = *buf.get(pos)?;
+    let first = *buf.get(pos + 1)? as usize;
+    if first < 0x80 {
+        return Some((tag, pos + 2, first));
+    }
+
+    let count = first & 0x7F;
+    if count == 0 || count > 4 {
+        return None;
+    }
+    let len = buf.get(pos + 2..pos + 2 + count)?
+        .iter()
+        .fold(0usize, |acc, b| (acc << 8) | *b as usize);
+    Some((tag, pos + 2 + count, len))
+}
+
+fn error_code(message: &[u8]) -> Option<u32> {
+    let (_, seq_start, _) = read_header(message, 0)?;
+    let (_, mut pos, seq_len) = read_header(message, seq_start)?;
+    let end = (pos + seq_len).min(message.len());
+
+    while pos < end {
+        let (tag, start, len) = read_header(message, pos)?;
+        if tag == 0xA6 {
+            let (_, int_start, int_len) = read_header(message, start)?;
+            let bytes = message.get(int_start..int_start + int_len)?;
+            return Some(bytes.iter().fold(0u32, |acc, b| (acc << 8) | *b as u32));
+        }
+        pos = start + len;
+    }
+    None
+}
+
+fn error_name(code: u32) -> &'static str {
+    match code {
+        6 => "KDC_ERR_C_PRINCIPAL_UNKNOWN",
+        7 => "KDC_ERR_S_PRINCIPAL_UNKNOWN",
+        14 => "KDC_ERR_ETYPE_NOSUPP",
+        24 => "KDC_ERR_PREAUTH_FAILED",
+        25 => "KDC_ERR_PREAUTH_REQUIRED",
+        68 => "KDC_ERR_WRONG_REALM",
+        _ => "KRB_ERROR",
+    }
+}
+
+pub(crate) async fn probe(stream: &mut TcpStream, timeout: Duration) -> Option<ProbeMatch> {
+    stream.write_all(&build_as_req(rand::random())).await.ok()?;
+
+    let mut buffer = [0u8; 4096];
+    let n = tokio::time::timeout(timeout, stream.read(&mut buffer)).await.ok()?.ok()?;
+    let message = buffer.get(4..n)?;
+
+    let banner = match message.first()? {
+        0x7E => {
+            let code = error_code(message)?;
+            format!("KRB-ERROR {} ({})", error_name(code), code)
+        }
+        0x6B => "AS-REP without pre-authentication".to_string(),
+        _ => return None,
+    };
+
+    Some(ProbeMatch {
+        banner,
+        service: "kerberos-sec Kerberos KDC".to_string(),
+        findings: Vec::new(),
+    })
+}
@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use colored::{ColoredString, Colorize};
+use futures::future::BoxFuture;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use crate::scanning::proxy::{self, Proxy};
+use crate::scanning::tcp;
+
+pub mod bacnet;
+pub mod database;
+pub mod docker;
+pub mod finger;
+pub mod grpc;
+pub mod haproxy;
+pub mod http;
+pub mod jupyter;
+pub mod kerberos;
+pub mod modbus;
+pub mod ntlm;
+pub mod resolver;
+pub mod source_control;
+pub mod syslog;
+pub mod udp_patterns;
+pub mod udp_probes;
+pub mod webdav;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub fn label(&self) -> ColoredString {
+        match self {
+            Severity::Medium => "[MEDIUM]".yellow(),
+            Severity::High => "[HIGH]".red(),
+            Severity::Critical => "[CRITICAL]".on_red(),
+        }
+    }
+}
+
+/// A security-relevant observation a probe made about a service.
+#[derive(Debug, Clone)]
+pub struct Finding {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Finding {
+    pub(crate) fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Finding { severity, message: message.into() }
+    }
+}
+
+/// What a protocol-specific probe learned from a service.
+pub struct ProbeMatch {
+    pub banner: String,
+    pub service: String,
+    pub findings: Vec<Finding>,
+}
+
+/// What a `ServiceProbe` reports. `None` leaves the open port without a banner.
+pub type ProbeResult = Option<ProbeMatch>;
+
+/// A probe registered with `ScanConfig::with_service_probes`. It replaces the built-in
+/// probe and client-first payload for the ports it declares, and gets the connected stream.
+pub trait ServiceProbe: Send + Sync {
+    fn matching_ports(&self) -> &[u16];
+
+    fn probe(&self, stream: TcpStream, timeout: Duration) -> BoxFuture<'_, ProbeResult>;
+}
+
+/// Sends a fixed payload, or nothing when it is empty, and reads the answer as the banner.
+/// Built from the entries of a `--probes` file.
+pub struct PayloadProbe {
+    ports: [u16; 1],
+    payload: Vec<u8>,
+}
+
+impl PayloadProbe {
+    pub fn new(port: u16, payload: Vec<u8>) -> Self {
+        PayloadProbe { ports: [port], payload }
+    }
+}
+
+impl ServiceProbe for PayloadProbe {
+    fn matching_ports(&self) -> &[u16] {
+        &self.ports
+    }
+
+    fn probe(&self, mut stream: TcpStream, timeout: Duration) -> BoxFuture<'_, ProbeResult> {
+        Box::pin(async move {
+            if !self.payload.is_empty() {
+                stream.write_all(&self.payload).await.ok()?;
+            }
+
+            let mut buffer = [0u8; 1024];
+            let n = tokio::time::timeout(timeout, stream.read(&mut buffer)).await.unwrap_or(Ok(0)).ok()?;
+            if n == 0 {
+                return None;
+            }
+
+            let banner = String::from_utf8_lossy(&buffer[..n]).to_string();
+            let service = tcp::get_service_name(&banner);
+            Some(ProbeMatch { banner, service, findings: Vec::new() })
+        })
+    }
+}
+
+pub(crate) const HTTP_GET: &[u8] = b"GET / HTTP/1.0\r\n\r\n";
+
+/// Ports whose services stay silent until the client sends something, with the payload
+/// sent before the banner read. Ports with a dedicated probe above are not listed here.
+pub(crate) fn default_client_first() -> HashMap<u16, Vec<u8>> {
+    let table: [(u16, &[u8]); 7] = [
+        (631, HTTP_GET),
+        (5601, HTTP_GET),
+        (9200, HTTP_GET),
+        (6379, b"PING\r\n"),
+        (11211, b"version\r\n"),
+        // PostgreSQL SSLRequest, answered with a single 'S' or 'N'
+        (5432, &[0x00, 0x00, 0x00, 0x08, 0x04, 0xD2, 0x16, 0x2F]),
+        (1883, &[0x10, 0x0C, 0x00, 0x04, b'M', b'Q', b'T', b'T', 0x04, 0x02, 0x00, 0x3C, 0x00, 0x00]),
+    ];
+
+    table.iter().map(|(port, payload)| (*port, payload.to_vec())).collect()
+}
+
+/// Ports whose services greet first but say more after a command, with the command sent
+/// once the greeting is read. Both answers together form the banner.
+pub(crate) fn default_greeting_replies() -> HashMap<u16, Vec<u8>> {
+    let table: [(u16, &[u8]); 6] = [
+        (21, b"FEAT\r\n"),
+        (25, b"EHLO webshot\r\n"),
+        (587, b"EHLO webshot\r\n"),
+        (110, b"CAPA\r\n"),
+        (143, b"a001 CAPABILITY\r\n"),
+        (4190, b"CAPABILITY\r\n"),
+    ];
+
+    table.iter().map(|(port, payload)| (*port, payload.to_vec())).collect()
+}
+
+/// Reads a `--probes` file: a JSON object mapping port numbers to the payload to send.
+/// Characters up to U+00FF are sent as single bytes, so binary payloads can be written as
+/// "\u0000" escapes. An empty payload only reads what the service sends on its own.
+pub fn load_client_first(path: &str) -> Result<HashMap<u16, Vec<u8>>, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    let entries: HashMap<String, String> = serde_json::from_str(&content).map_err(|e| format!("{}: {}", path, e))?;
+
+    let mut probes = HashMap::new();
+    for (port, payload) in entries {
+        let port: u16 = port.parse().map_err(|_| format!("{}: '{}' is not a port number", path, port))?;
+        let bytes = payload
+            .chars()
+            .map(|c| u8::try_from(u32::from(c)).map_err(|_| format!("{}: port {} payload has a character above U+00FF", path, port)))
+            .collect::<Result<Vec<u8>, String>>()?;
+        probes.insert(port, bytes);
+    }
+    Ok(probes)
+}
+
+/// The service being probed, with what is needed to open follow-up connections to it.
+pub(crate) struct ProbeTarget<'a> {
+    pub(crate) addr: &'a str,
+    pub(crate) port: u16,
+    pub(crate) timeout: Duration,
+    pub(crate) proxy: Option<&'a Proxy>,
+    /// Sent as the User-Agent of HTTP requests
+    pub(crate) user_agent: Option<&'a str>,
+}
+
+impl ProbeTarget<'_> {
+    pub(crate) async fn connect(&self) -> Option<TcpStream> {
+        tokio::time::timeout(self.timeout, proxy::connect(self.addr, self.proxy)).await.ok()?.ok()
+    }
+}
+
+/// Runs the dedicated probe for the target port, if there is one, on an already connected stream.
+/// Returns `None` when the port has no probe or the service did not answer like the protocol.
+pub(crate) async fn probe_tcp(target: &ProbeTarget<'_>, stream: &mut TcpStream) -> Option<ProbeMatch> {
+    match target.port {
+        finger::FINGER_PORT => finger::probe(stream, target.timeout, None).await,
+        88 => kerberos::probe(stream, target.timeout).await,
+        502 => modbus::probe(stream, target.timeout).await,
+        601 => syslog::probe_tcp(stream, target).await,
+        port if http::HTTP_PORTS.contains(&port) => http::probe(target, stream).await,
+        port if grpc::GRPC_PORTS.contains(&port) => grpc::probe(target, stream).await,
+        _ => None,
+    }
+}
+
+/// Runs the dedicated UDP probe for the target port on an unconnected socket.
+/// The outer `None` means the port has no probe and should get the generic payload,
+/// the inner one that the probe got no usable answer.
+pub(crate) async fn probe_udp(target: &ProbeTarget<'_>, socket: &UdpSocket) -> Option<Option<ProbeMatch>> {
+    match target.port {
+        53 => Some(resolver::probe(socket, target).await),
+        514 => Some(syslog::probe_udp(socket, target).await),
+        47808 => Some(bacnet::probe(socket, target).await),
+        _ => None,
+    }
+}
@@ -0,0 +1,90 @@
+use std::fmt;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use crate::scanning::protocols::{Finding, ProbeMatch, Severity};
+
+// 0xFF is the "not significant" unit id the spec recommends for devices reached directly over TCP
+const UNIT_ID: u8 = 0xFF;
+
+#[derive(Debug, Clone, Default)]
+pub struct ModbusServiceInfo {
+    pub vendor_name: String,
+    pub product_code: String,
+    pub major_revision: String,
+    pub minor_revision: String,
+    pub unit_id: u8,
+}
+
+impl fmt::Display for ModbusServiceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Modbus unit {} (vendor: {}, product: {}, revision: {}.{})",
+            self.unit_id, self.vendor_name, self.product_code, self.major_revision, self.minor_revision
+        )
+    }
+}
+
+/// MBAP header followed by Read Device Identification (function 43, MEI type 14, basic objects).
+fn build_read_device_id() -> [u8; 11] {
+    [0x00, 0x01, 0x00, 0x00, 0x00, 0x05, UNIT_ID, 0x2B, 0x0E, 0x01, 0x00]
+}
+
+fn split_revision(revision: &str) -> (String, String) {
+    let revision = revision.trim().trim_start_matches(['V', 'v']);
+    match revision.split_once('.') {
+        Some((major, minor)) => (major.to_string(), minor.to_string()),
+        None => (revision.to_string(), String::new()),
+    }
+}
+
+fn parse_device_id(response: &[u8]) -> Option<ModbusServiceInfo> {
+    // MBAP (7) + function, MEI type, read code, conformity, more follows, next object, object count
+    let pdu = response.get(7..)?;
+    if pdu.len() < 7 || pdu[0] != 0x2B || pdu[1] != 0x0E {
+        return None;
+    }
+
+    let mut info = ModbusServiceInfo { unit_id: response[6], ..Default::default() };
+    let mut pos = 7;
+    for _ in 0..pdu[6] {
+        let id = *pdu.get(pos)?;
+        let len = *pdu.get(pos + 1)? as usize;
+        let value = String::from_utf8_lossy(pdu.get(pos + 2..pos + 2 + len)?).to_string();
+        match id {
+            0x00 => info.vendor_name = value,
+            0x01 => info.product_code = value,
+            0x02 => (info.major_revision, info.minor_revision) = split_revision(&value),
+            _ => {}
+        }
+        pos += 2 + len;
+    }
+
+    Some(info)
+}
+
+pub(crate) async fn probe(stream: &mut TcpStream, timeout: Duration) -> Option<ProbeMatch> {
+    stream.write_all(&build_read_device_id()).await.ok()?;
+
+    let mut buffer = [0u8; 512];
+    let n = tokio::time::timeout(timeout, stream.read(&mut buffer)).await.ok()?.ok()?;
+    let response = &buffer[..n];
+
+    // Transaction id echoed and protocol id 0 means something speaks Modbus TCP
+    if n < 9 || response[..4] != [0x00, 0x01, 0x00, 0x00] {
+        return None;
+    }
+
+    let banner = match parse_device_id(response) {
+        Some(info) => info.to_string(),
+        None if response[7] == 0xAB => format!("Modbus unit {} (exception code {})", response[6], response[8]),
+        None => return None,
+    };
+
+    Some(ProbeMatch {
+        banner,
+        service: "modbus Modbus TCP".to_string(),
+        findings: vec![Finding::new(Severity::High, "Industrial Control System (Modbus) device reachable")],
+    })
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use crate::scanning::protocols::http::{self, AuthMethod, HttpAuthInfo};
+use crate::scanning::protocols::{Finding, ProbeTarget, Severity};
+
+const SIGNATURE: &[u8] = b"NTLMSSP\0";
+
+// UNICODE | OEM | REQUEST_TARGET | NTLM | ALWAYS_SIGN | EXTENDED_SESSIONSECURITY | VERSION | 128 | 56
+const NEGOTIATE_FLAGS: u32 = 0xA208_8207;
+const NEGOTIATE_VERSION: u32 = 0x0200_0000;
+
+// AV_PAIR ids in the challenge's target info
+const AV_EOL: u16 = 0;
+const AV_NB_COMPUTER_NAME: u16 = 1;
+const AV_NB_DOMAIN_NAME: u16 = 2;
+const AV_DNS_COMPUTER_NAME: u16 = 3;
+const AV_DNS_DOMAIN_NAME: u16 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WindowsVersion {
+    pub major: u8,
+    pub minor: u8,
+    pub build: u16,
+}
+
+impl fmt::Display for WindowsVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match (self.major, self.minor) {
+            (5, 1) => "Windows XP",
+            (5, 2) => "Windows Server 2003",
+            (6, 0) => "Windows Vista / Server 2008",
+            (6, 1) => "Windows 7 / Server 2008 R2",
+            (6, 2) => "Windows 8 / Server 2012",
+            (6, 3) => "Windows 8.1 / Server 2012 R2",
+            (10, 0) => "Windows 10 / Server 2016 or later",
+            _ => "Windows",
+        };
+        write!(f, "{} ({}.{} build {})", name, self.major, self.minor, self.build)
+    }
+}
+
+/// What an NTLM Challenge (Type 2) message tells about the server before any credentials are sent.
+#[derive(Debug, Clone, Default)]
+pub struct NtlmInfo {
+    pub domain: Option<String>,
+    pub computer_name: Option<String>,
+    pub dns_domain: Option<String>,
+    pub dns_computer_name: Option<String>,
+    pub os_version: Option<WindowsVersion>,
+}
+
+impl NtlmInfo {
+    pub(crate) fn findings(&self) -> Vec<Finding> {
+        vec![Finding::new(Severity::Medium, "NTLM challenge discloses the host name, domain and Windows version without authentication")]
+    }
+}
+
+impl fmt::Display for NtlmInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields = [
+            ("computer", &self.computer_name),
+            ("domain", &self.domain),
+            ("DNS computer", &self.dns_computer_name),
+            ("DNS domain", &self.dns_domain),
+        ];
+        let mut parts: Vec<String> = fields
+            .iter()
+            .filter_map(|(label, value)| value.as_ref().map(|value| format!("{}: {}", label, value)))
+            .collect();
+        if let Some(version) = &self.os_version {
+            parts.push(format!("OS: {}", version));
+        }
+        write!(f, "NTLM ({})", parts.join(", "))
+    }
+}
+
+/// Negotiate (Type 1) message without domain or workstation, with a Windows 7 version block.
+fn negotiate_message() -> Vec<u8> {
+    let mut message = SIGNATURE.to_vec();
+    message.extend_from_slice(&1u32.to_le_bytes());
+    message.extend_from_slice(&NEGOTIATE_FLAGS.to_le_bytes());
+    message.extend_from_slice(&[0; 16]); // empty domain and workstation fields
+    message.extend_from_slice(&[0x06, 0x01, 0xB1, 0x1D, 0x00, 0x00, 0x00, 0x0F]);
+    message
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn utf16(data: &[u8]) -> String {
+    let units: Vec<u16> = data.chunks_exact(2).map(|pair| u16::from_le_bytes([pair[0], pair[1]])).collect();
+    String::from_utf16_lossy(&units)
+}
+
+/// Parses a Challenge (Type 2) message: the target info AV pairs name the host and domain,
+/// and the version block carries the Windows version when the server sets NEGOTIATE_VERSION.
+fn parse_challenge(message: &[u8]) -> Option<NtlmInfo> {
+    if !message.starts_with(SIGNATURE) || u32_at(message, 8)? != 2 {
+        return None;
+    }
+
+    let flags = u32_at(message, 20)?;
+    let mut info = NtlmInfo::default();
+
+    if flags & NEGOTIATE_VERSION != 0 {
+        if let Some(version) = message.get(48..52) {
+            info.os_version = Some(WindowsVersion { major: version[0], minor: version[1], build: u16::from_le_bytes([version[2], version[3]]) });
+        }
+    }
+
+    let info_len = u16_at(message, 40)? as usize;
+    let info_offset = u32_at(message, 44)? as usize;
+    let target_info = message.get(info_offset..info_offset + info_len).unwrap_or_default();
+
+    let mut pos = 0;
+    while let (Some(id), Some(len)) = (u16_at(target_info, pos), u16_at(target_info, pos + 2)) {
+        if id == AV_EOL {
+            break;
+        }
+        let Some(value) = target_info.get(pos + 4..pos + 4 + len as usize) else {
+            break;
+        };
+        let value = Some(utf16(value));
+        match id {
+            AV_NB_COMPUTER_NAME => info.computer_name = value,
+            AV_NB_DOMAIN_NAME => info.domain = value,
+            AV_DNS_COMPUTER_NAME => info.dns_computer_name = value,
+            AV_DNS_DOMAIN_NAME => info.dns_domain = value,
+            _ => {}
+        }
+        pos += 4 + len as usize;
+    }
+
+    Some(info)
+}
+
+/// Starts an NTLM handshake against a server that offered NTLM or Negotiate and reads the
+/// challenge. Authentication is never completed.
+pub(crate) async fn detect(target: &ProbeTarget<'_>, challenges: &[HttpAuthInfo]) -> Option<NtlmInfo> {
+    let scheme = if challenges.iter().any(|challenge| challenge.method == AuthMethod::Ntlm) {
+        "NTLM"
+    } else if challenges.iter().any(|challenge| challenge.method == AuthMethod::Negotiate) {
+        // Windows accepts a raw NTLMSSP token in place of SPNEGO
+        "Negotiate"
+    } else {
+        return None;
+    };
+
+    let authorization = format!("{} {}", scheme, STANDARD.encode(negotiate_message()));
+    let response = http::request(target, &http::build_request("GET", "/", target.addr, &[("Authorization", &authorization)])).await?;
+
+    let token = response
+        .headers
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case("WWW-Authenticate"))
+        .find_map(|(_, value)| value.strip_prefix(scheme)?.trim().split(',').next().map(str::trim))?;
+
+    parse_challenge(&STANDARD.decode(token).ok()?)
+}
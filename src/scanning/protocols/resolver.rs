@@ -0,0 +1,59 @@
+use std::fmt;
+use tokio::net::UdpSocket;
+use crate::scanning::dns::{build_query, parse_message, RecordData, TYPE_A};
+use crate::scanning::protocols::{Finding, ProbeMatch, ProbeTarget, Severity};
+
+// A name the target cannot be authoritative for, so an answer means it recursed
+const EXTERNAL_NAME: &str = "example.com";
+
+#[derive(Debug, Clone)]
+pub struct DnsResolverInfo {
+    pub recursion_available: bool,
+    pub open_resolver: bool,
+}
+
+impl fmt::Display for DnsResolverInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DNS server")?;
+        if self.open_resolver {
+            write!(f, " (open recursive resolver)")
+        } else if self.recursion_available {
+            write!(f, " (recursion advertised, external query not answered)")
+        } else {
+            write!(f, " (recursion refused)")
+        }
+    }
+}
+
+/// Sends a recursive query for an external name. The server is an open resolver when
+/// it advertises recursion (RA) and actually returns an address for the name.
+pub(crate) async fn probe(socket: &UdpSocket, target: &ProbeTarget<'_>) -> Option<ProbeMatch> {
+    let id: u16 = rand::random();
+    socket.send_to(&build_query(id, EXTERNAL_NAME, TYPE_A, true), target.addr).await.ok()?;
+
+    let mut buffer = [0u8; 1500];
+    let message = loop {
+        let (n, _) = tokio::time::timeout(target.timeout, socket.recv_from(&mut buffer)).await.ok()?.ok()?;
+        match parse_message(&buffer[..n]) {
+            Some(message) if message.id == id => break message,
+            _ => continue,
+        }
+    };
+
+    let answered = message.rcode == 0 && message.records.iter().any(|record| matches!(record.data, RecordData::A(_)));
+    let info = DnsResolverInfo {
+        recursion_available: message.recursion_available,
+        open_resolver: message.recursion_available && answered,
+    };
+
+    let mut findings = Vec::new();
+    if info.open_resolver {
+        findings.push(Finding::new(Severity::High, "Open recursive DNS resolver, usable for amplification attacks"));
+    }
+
+    Some(ProbeMatch {
+        banner: info.to_string(),
+        service: "domain".to_string(),
+        findings,
+    })
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use serde_json::Value;
+use crate::scanning::protocols::http::{self, HttpResponse};
+use crate::scanning::protocols::{Finding, ProbeTarget, Severity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceControlPlatform {
+    GitLab,
+    GitHubEnterprise,
+    Gitea,
+}
+
+impl SourceControlPlatform {
+    fn version_path(&self) -> Option<&'static str> {
+        match self {
+            SourceControlPlatform::GitLab => Some("/api/v4/version"),
+            SourceControlPlatform::Gitea => Some("/api/v1/version"),
+            SourceControlPlatform::GitHubEnterprise => None,
+        }
+    }
+
+    fn sign_up_path(&self) -> &'static str {
+        match self {
+            SourceControlPlatform::GitLab => "/users/sign_up",
+            SourceControlPlatform::GitHubEnterprise => "/join",
+            SourceControlPlatform::Gitea => "/user/sign_up",
+        }
+    }
+}
+
+impl fmt::Display for SourceControlPlatform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceControlPlatform::GitLab => write!(f, "GitLab"),
+            SourceControlPlatform::GitHubEnterprise => write!(f, "GitHub Enterprise"),
+            SourceControlPlatform::Gitea => write!(f, "Gitea"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SourceControlServiceInfo {
+    pub platform: SourceControlPlatform,
+    pub version: Option<String>,
+    pub registration_enabled: bool,
+}
+
+impl SourceControlServiceInfo {
+    pub(crate) fn findings(&self) -> Vec<Finding> {
+        if self.registration_enabled {
+            vec![Finding::new(Severity::Medium, format!("{} allows anyone to register an account", self.platform))]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl fmt::Display for SourceControlServiceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.platform)?;
+        if let Some(version) = &self.version {
+            write!(f, " {}", version)?;
+        }
+        if self.registration_enabled {
+            write!(f, " (open registration)")?;
+        }
+        Ok(())
+    }
+}
+
+fn identify(root: &HttpResponse) -> Option<SourceControlPlatform> {
+    let body = root.body.to_lowercase();
+
+    if root.header("X-Gitlab-Meta").is_some()
+        || root.header("Location").is_some_and(|location| location.ends_with("/users/sign_in"))
+        || body.contains("content=\"gitlab\"")
+    {
+        Some(SourceControlPlatform::GitLab)
+    } else if body.contains("<meta content=\"github\" property=\"og:site_name\"") || root.header("X-GitHub-Request-Id").is_some() {
+        Some(SourceControlPlatform::GitHubEnterprise)
+    } else if body.contains("gitea - git with a cup of tea") || body.contains("powered by gitea") {
+        Some(SourceControlPlatform::Gitea)
+    } else {
+        None
+    }
+}
+
+async fn get(target: &ProbeTarget<'_>, path: &str) -> Option<HttpResponse> {
+    http::request(target, &http::build_request("GET", path, target.addr, &[])).await
+}
+
+/// Identifies the platform from the response to `GET /`, then tries the unauthenticated
+/// version endpoint and the sign-up page. Gitea pages that hide their branding are still
+/// recognised by the settings API.
+pub(crate) async fn detect(target: &ProbeTarget<'_>, root: &HttpResponse) -> Option<SourceControlServiceInfo> {
+    let platform = match identify(root) {
+        Some(platform) => platform,
+        None => {
+            let settings = get(target, "/api/v1/settings/api").await.filter(|response| response.status == 200)?;
+            let settings: Value = serde_json::from_str(&settings.body).ok()?;
+            settings.get("max_response_items")?;
+            SourceControlPlatform::Gitea
+        }
+    };
+
+    let version = match platform.version_path() {
+        Some(path) => get(target, path)
+            .await
+            .filter(|response| response.status == 200)
+            .and_then(|response| serde_json::from_str::<Value>(&response.body).ok())
+            .and_then(|json| json.get("version")?.as_str().map(str::to_string)),
+        None => root.header("X-GitHub-Enterprise-Version").map(str::to_string),
+    };
+
+    let registration_enabled = get(target, platform.sign_up_path()).await.is_some_and(|response| {
+        let body = response.body.to_lowercase();
+        response.status == 200 && body.contains("password") && !body.contains("registration is disabled")
+    });
+
+    Some(SourceControlServiceInfo { platform, version, registration_enabled })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+    use crate::scanning::testing::http_server;
+
+    fn response(raw: &str) -> HttpResponse {
+        http::parse_response(raw.as_bytes()).unwrap()
+    }
+
+    async fn detect_on(routes: &'static [(&'static str, &'static str)]) -> Option<SourceControlServiceInfo> {
+        let port = http_server(routes).await;
+        let addr = format!("127.0.0.1:{}", port);
+        let target = ProbeTarget { addr: &addr, port, timeout: Duration::from_millis(500), proxy: None, user_agent: None };
+        let root = http::request(&target, &http::build_request("GET", "/", &addr, &[])).await?;
+        detect(&target, &root).await
+    }
+
+    #[test]
+    fn identifies_platforms_from_the_root_page() {
+        let gitlab = response("HTTP/1.1 302 Found\r\nLocation: http://git.example/users/sign_in\r\n\r\n");
+        assert_eq!(identify(&gitlab), Some(SourceControlPlatform::GitLab));
+
+        let github = response("HTTP/1.1 200 OK\r\n\r\n<meta content=\"GitHub\" property=\"og:site_name\">");
+        assert_eq!(identify(&github), Some(SourceControlPlatform::GitHubEnterprise));
+
+        let gitea = response("HTTP/1.1 200 OK\r\n\r\n<title>Gitea - Git with a cup of tea</title>");
+        assert_eq!(identify(&gitea), Some(SourceControlPlatform::Gitea));
+
+        let other = response("HTTP/1.1 200 OK\r\nServer: nginx\r\n\r\n<title>Welcome to nginx!</title>");
+        assert_eq!(identify(&other), None);
+    }
+
+    #[tokio::test]
+    async fn gitlab_version_and_open_registration() {
+        let info = detect_on(&[
+            ("/", "HTTP/1.0 200 OK\r\nX-Gitlab-Meta: {}\r\n\r\n"),
+            ("/api/v4/version", "HTTP/1.0 200 OK\r\n\r\n{\"version\":\"16.8.1-ee\",\"revision\":\"a1b2c3\"}"),
+            ("/users/sign_up", "HTTP/1.0 200 OK\r\n\r\n<input type=\"password\" name=\"new_user[password]\">"),
+        ])
+        .await
+        .unwrap();
+
+        assert_eq!(info.to_string(), "GitLab 16.8.1-ee (open registration)");
+        assert_eq!(info.findings().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn github_enterprise_version_comes_from_the_header() {
+        let info = detect_on(&[("/", "HTTP/1.0 200 OK\r\nX-GitHub-Request-Id: 1\r\nX-GitHub-Enterprise-Version: 3.11.2\r\n\r\n")])
+            .await
+            .unwrap();
+
+        assert_eq!(info.to_string(), "GitHub Enterprise 3.11.2");
+        assert!(info.findings().is_empty());
+    }
+
+    #[tokio::test]
+    async fn unbranded_gitea_is_found_through_the_settings_api() {
+        let info = detect_on(&[
+            ("/", "HTTP/1.0 200 OK\r\n\r\n<title>Code</title>"),
+            ("/api/v1/settings/api", "HTTP/1.0 200 OK\r\n\r\n{\"max_response_items\":50,\"default_paging_num\":30}"),
+            ("/api/v1/version", "HTTP/1.0 200 OK\r\n\r\n{\"version\":\"1.21.4\"}"),
+            ("/user/sign_up", "HTTP/1.0 200 OK\r\n\r\nRegistration is disabled. Please contact your site administrator. password"),
+        ])
+        .await
+        .unwrap();
+
+        assert_eq!(info.platform, SourceControlPlatform::Gitea);
+        assert_eq!(info.to_string(), "Gitea 1.21.4");
+        assert!(!info.registration_enabled);
+    }
+
+    #[tokio::test]
+    async fn unrecognised_service_is_not_reported() {
+        assert!(detect_on(&[("/", "HTTP/1.0 200 OK\r\n\r\n<title>Code</title>")]).await.is_none());
+    }
+}
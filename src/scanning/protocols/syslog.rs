@@ -0,0 +1,125 @@
+use std::fmt;
+use chrono::{SecondsFormat, Utc};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use crate::scanning::protocols::{Finding, ProbeMatch, ProbeTarget, Severity};
+
+#[derive(Debug, Clone)]
+pub struct SyslogServiceInfo {
+    pub protocol: &'static str,
+    pub accepts_unauthenticated: bool,
+}
+
+impl fmt::Display for SyslogServiceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Syslog over {}", self.protocol)?;
+        if self.accepts_unauthenticated {
+            write!(f, " (accepts unauthenticated messages)")?;
+        }
+        Ok(())
+    }
+}
+
+/// RFC 5424 message at facility user, severity informational.
+fn probe_message() -> String {
+    format!(
+        "<14>1 {} webshot - - - - Probe message",
+        Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true)
+    )
+}
+
+fn probe_match(info: SyslogServiceInfo, banner: String) -> ProbeMatch {
+    let mut findings = Vec::new();
+    if info.accepts_unauthenticated {
+        findings.push(Finding::new(Severity::Medium, "Plaintext syslog accepts messages, logged data may contain credentials"));
+    }
+
+    ProbeMatch {
+        banner: if banner.is_empty() { info.to_string() } else { format!("{}; {}", info, banner) },
+        service: "syslog".to_string(),
+        findings,
+    }
+}
+
+/// UDP syslog never answers, so the socket is connected to surface an ICMP port
+/// unreachable as an error; silence is reported as open|filtered.
+pub(crate) async fn probe_udp(socket: &UdpSocket, target: &ProbeTarget<'_>) -> Option<ProbeMatch> {
+    socket.connect(target.addr).await.ok()?;
+    socket.send(format!("{}\n", probe_message()).as_bytes()).await.ok()?;
+
+    let mut buffer = [0u8; 1024];
+    let (accepted, banner) = match tokio::time::timeout(target.timeout, socket.recv(&mut buffer)).await {
+        Ok(Ok(n)) => (true, String::from_utf8_lossy(&buffer[..n]).trim().to_string()),
+        // ECONNREFUSED here is the ICMP port unreachable
+        Ok(Err(_)) => return None,
+        // The unreachable can also be left pending on the socket without waking the read
+        Err(_) if matches!(socket.take_error(), Ok(Some(_))) => return None,
+        // A firewall dropping the datagram looks the same, so silence proves nothing was accepted
+        Err(_) => (false, "no ICMP unreachable (open|filtered)".to_string()),
+    };
+
+    let info = SyslogServiceInfo { protocol: "udp", accepts_unauthenticated: accepted };
+    Some(probe_match(info, banner))
+}
+
+/// Sends one octet-counted frame (RFC 6587) and waits for the server to either
+/// stay quiet, answer, or drop the connection.
+pub(crate) async fn probe_tcp(stream: &mut TcpStream, target: &ProbeTarget<'_>) -> Option<ProbeMatch> {
+    let message = probe_message();
+    stream.write_all(format!("{} {}", message.len(), message).as_bytes()).await.ok()?;
+
+    let mut buffer = [0u8; 1024];
+    let (accepted, banner) = match tokio::time::timeout(target.timeout, stream.read(&mut buffer)).await {
+        Ok(Ok(0)) | Ok(Err(_)) => (false, String::new()),
+        Ok(Ok(n)) => (true, String::from_utf8_lossy(&buffer[..n]).trim().to_string()),
+        Err(_) => (true, String::new()),
+    };
+
+    let info = SyslogServiceInfo { protocol: "tcp", accepts_unauthenticated: accepted };
+    Some(probe_match(info, banner))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use super::*;
+    use crate::scanning::testing::udp_reply_server;
+
+    async fn probe_from(socket: &UdpSocket, port: u16) -> Option<ProbeMatch> {
+        let addr = format!("127.0.0.1:{}", port);
+        let target = ProbeTarget { addr: &addr, port, timeout: Duration::from_millis(500), proxy: None, user_agent: None };
+        probe_udp(socket, &target).await
+    }
+
+    async fn probe(port: u16) -> Option<ProbeMatch> {
+        probe_from(&UdpSocket::bind("127.0.0.1:0").await.unwrap(), port).await
+    }
+
+    #[tokio::test]
+    async fn reply_means_messages_are_accepted() {
+        let port = udp_reply_server(b"ack").await;
+        let found = probe(port).await.unwrap();
+
+        assert_eq!(found.service, "syslog");
+        assert_eq!(found.banner, "Syslog over udp (accepts unauthenticated messages); ack");
+        assert_eq!(found.findings.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn silence_is_open_filtered_without_finding() {
+        let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let found = probe(silent.local_addr().unwrap().port()).await.unwrap();
+
+        assert_eq!(found.banner, "Syslog over udp; no ICMP unreachable (open|filtered)");
+        assert!(found.findings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn port_unreachable_is_closed() {
+        // Bound first, so it cannot be handed the port released below
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = UdpSocket::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        assert!(probe_from(&socket, port).await.is_none());
+    }
+}
+
@@ -0,0 +1,89 @@
+use std::sync::OnceLock;
+
+/// Built-in UDP response signatures. Each pattern is a space separated list of tokens
+/// matched from the first byte of the response:
+///
+/// - `81` matches that byte exactly, `??` matches any byte
+/// - `04&07` matches when `byte & 0x07 == 0x04`
+/// - `"SIP/2.0"` matches the literal text (no spaces)
+///
+/// The first matching entry wins, so more specific patterns go first.
+const PATTERNS: &[(&str, usize, &str)] = &[
+    ("domain", 12, "?? ?? 80&f8"),
+    ("ntp", 48, "04&07 00&f0"),
+    ("snmp", 8, "30 ?? 02 01"),
+    ("sip", 7, "\"SIP/2.0\""),
+    ("ssdp", 8, "\"HTTP/1.\""),
+    ("asf-rmcp", 4, "06 00 ff 07"),
+    ("tftp", 4, "00 05 00"),
+    ("quic", 7, "80&80 00 00 00 00"),
+    ("quic", 7, "c0&c0 00 00 00 01"),
+    ("openvpn", 14, "40&f8"),
+];
+
+#[derive(Debug)]
+enum Token {
+    Any,
+    Masked { value: u8, mask: u8 },
+}
+
+#[derive(Debug)]
+struct UdpPattern {
+    service: &'static str,
+    min_len: usize,
+    tokens: Vec<Token>,
+}
+
+impl UdpPattern {
+    fn matches(&self, response: &[u8]) -> bool {
+        response.len() >= self.min_len
+            && response.len() >= self.tokens.len()
+            && self.tokens.iter().zip(response).all(|(token, byte)| match token {
+                Token::Any => true,
+                Token::Masked { value, mask } => byte & mask == *value,
+            })
+    }
+}
+
+fn parse_pattern(pattern: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for part in pattern.split_whitespace() {
+        if let Some(text) = part.strip_prefix('"').and_then(|part| part.strip_suffix('"')) {
+            tokens.extend(text.bytes().map(|byte| Token::Masked { value: byte, mask: 0xFF }));
+            continue;
+        }
+
+        let token = match part.split_once('&') {
+            _ if part == "??" => Token::Any,
+            Some((value, mask)) => Token::Masked {
+                value: u8::from_str_radix(value, 16).expect("invalid UDP pattern byte"),
+                mask: u8::from_str_radix(mask, 16).expect("invalid UDP pattern mask"),
+            },
+            None => Token::Masked { value: u8::from_str_radix(part, 16).expect("invalid UDP pattern byte"), mask: 0xFF },
+        };
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// Maps raw UDP responses to service names using the signatures in `PATTERNS`.
+#[derive(Debug)]
+pub(crate) struct UdpResponseLibrary {
+    patterns: Vec<UdpPattern>,
+}
+
+impl UdpResponseLibrary {
+    pub(crate) fn builtin() -> &'static UdpResponseLibrary {
+        static LIBRARY: OnceLock<UdpResponseLibrary> = OnceLock::new();
+        LIBRARY.get_or_init(|| UdpResponseLibrary {
+            patterns: PATTERNS
+                .iter()
+                .map(|(service, min_len, pattern)| UdpPattern { service, min_len: *min_len, tokens: parse_pattern(pattern) })
+                .collect(),
+        })
+    }
+
+    pub(crate) fn identify(&self, response: &[u8]) -> Option<&'static str> {
+        self.patterns.iter().find(|pattern| pattern.matches(response)).map(|pattern| pattern.service)
+    }
+}
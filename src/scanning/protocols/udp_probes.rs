@@ -0,0 +1,39 @@
+/// Sent to ports without a protocol specific payload.
+const GENERIC_PAYLOAD: &[u8] = b"Ping";
+
+/// Builds the payload the generic UDP scan sends, so that common services get a request
+/// they actually answer. Ports with a dedicated probe (see `probe_udp`) never reach this.
+pub struct UdpProbeFactory;
+
+impl UdpProbeFactory {
+    pub fn build(port: u16) -> Vec<u8> {
+        match port {
+            123 => ntp_request(),
+            161 => snmp_get(),
+            _ => GENERIC_PAYLOAD.to_vec(),
+        }
+    }
+}
+
+/// 48 byte NTP packet with LI 0, version 4, mode 3 (client) and the rest zeroed.
+fn ntp_request() -> Vec<u8> {
+    let mut packet = vec![0u8; 48];
+    packet[0] = 0x23;
+    packet
+}
+
+/// SNMPv1 GetRequest for sysDescr.0 with the "public" community.
+fn snmp_get() -> Vec<u8> {
+    vec![
+        0x30, 0x26, // message
+        0x02, 0x01, 0x00, // version 1
+        0x04, 0x06, b'p', b'u', b'b', b'l', b'i', b'c', // community
+        0xA0, 0x19, // GetRequest PDU
+        0x02, 0x01, 0x01, // request id
+        0x02, 0x01, 0x00, // error status
+        0x02, 0x01, 0x00, // error index
+        0x30, 0x0E, 0x30, 0x0C, // variable bindings
+        0x06, 0x08, 0x2B, 0x06, 0x01, 0x02, 0x01, 0x01, 0x01, 0x00, // 1.3.6.1.2.1.1.1.0
+        0x05, 0x00, // NULL
+    ]
+}
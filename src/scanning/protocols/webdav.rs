@@ -0,0 +1,71 @@
+use std::fmt;
+use regex::Regex;
+use crate::scanning::protocols::{http, Finding, ProbeTarget, Severity};
+
+#[derive(Debug, Clone)]
+pub struct WebDavInfo {
+    pub dav_class: String,
+    pub methods: Vec<String>,
+    pub resources: Vec<String>,
+    pub write_allowed_without_auth: bool,
+}
+
+impl WebDavInfo {
+    pub(crate) fn findings(&self) -> Vec<Finding> {
+        if self.write_allowed_without_auth {
+            vec![Finding::new(Severity::Critical, "WebDAV allows PUT without authentication")]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+impl fmt::Display for WebDavInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "WebDAV (DAV: {}, methods: {}", self.dav_class, self.methods.join(" "))?;
+        if !self.resources.is_empty() {
+            write!(f, ", resources: {}", self.resources.join(" "))?;
+        }
+        write!(f, ")")
+    }
+}
+
+fn parse_hrefs(body: &str) -> Vec<String> {
+    let href = Regex::new(r"(?i)<(?:[a-z0-9]+:)?href>\s*([^<]+?)\s*</(?:[a-z0-9]+:)?href>").unwrap();
+    href.captures_iter(body).map(|cap| cap[1].to_string()).collect()
+}
+
+/// Sends OPTIONS and, when the server advertises DAV, a depth 1 PROPFIND on the root.
+/// PUT is never attempted: writability is judged from the advertised methods and whether
+/// PROPFIND works without credentials.
+pub(crate) async fn detect(target: &ProbeTarget<'_>) -> Option<WebDavInfo> {
+    let options = http::request(target, &http::build_request("OPTIONS", "/", target.addr, &[])).await?;
+    let dav_class = options.header("DAV")?.to_string();
+
+    let methods: Vec<String> = options
+        .header("Allow")
+        .or_else(|| options.header("Public"))
+        .unwrap_or("")
+        .split(',')
+        .map(|method| method.trim().to_uppercase())
+        .filter(|method| !method.is_empty())
+        .collect();
+
+    let propfind = http::request(
+        target,
+        &http::build_request("PROPFIND", "/", target.addr, &[("Depth", "1"), ("Content-Length", "0")]),
+    ).await;
+
+    let unauthenticated = propfind.as_ref().is_some_and(|response| response.status != 401 && response.status != 403);
+    let resources = propfind
+        .filter(|response| response.status == 207)
+        .map(|response| parse_hrefs(&response.body))
+        .unwrap_or_default();
+
+    Some(WebDavInfo {
+        dav_class,
+        write_allowed_without_auth: unauthenticated && methods.iter().any(|method| method == "PUT"),
+        methods,
+        resources,
+    })
+}
@@ -0,0 +1,117 @@
+use std::io::{Error, ErrorKind};
+use std::net::IpAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use url::Url;
+
+#[derive(Debug, Clone)]
+pub enum Proxy {
+    /// HTTP proxy tunnelling through `CONNECT`
+    Http(String),
+    Socks5(String),
+}
+
+impl Proxy {
+    pub fn parse(proxy_url: &str) -> Result<Proxy, String> {
+        let url = Url::parse(proxy_url).map_err(|e| format!("{} ({})", proxy_url, e))?;
+        let host = url.host_str().ok_or_else(|| format!("{} has no host", proxy_url))?;
+        let port = url.port_or_known_default().unwrap_or(1080);
+        let addr = format!("{}:{}", host, port);
+
+        match url.scheme() {
+            "http" => Ok(Proxy::Http(addr)),
+            "socks5" | "socks5h" => Ok(Proxy::Socks5(addr)),
+            scheme => Err(format!("unsupported proxy scheme '{}' (expected http or socks5)", scheme)),
+        }
+    }
+}
+
+fn split_addr(addr: &str) -> Result<(&str, u16), Error> {
+    let (host, port) = addr
+        .rsplit_once(':')
+        .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "missing port"))?;
+    let port = port
+        .parse()
+        .map_err(|_| Error::new(ErrorKind::InvalidInput, "invalid port"))?;
+    Ok((host.trim_start_matches('[').trim_end_matches(']'), port))
+}
+
+async fn connect_http(proxy_addr: &str, target: &str) -> Result<TcpStream, Error> {
+    let mut stream = TcpStream::connect(proxy_addr).await?;
+    let request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, target);
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read byte by byte so nothing the target sends after the proxy reply is swallowed
+    let mut head = Vec::new();
+    let mut byte = [0u8; 1];
+    while !head.ends_with(b"\r\n\r\n") {
+        if stream.read(&mut byte).await? == 0 || head.len() > 8192 {
+            return Err(Error::new(ErrorKind::ConnectionAborted, "proxy closed the connection"));
+        }
+        head.push(byte[0]);
+    }
+
+    let status_line = String::from_utf8_lossy(&head);
+    match status_line.split_whitespace().nth(1) {
+        Some("200") => Ok(stream),
+        _ => Err(Error::new(ErrorKind::ConnectionRefused, "proxy refused CONNECT")),
+    }
+}
+
+async fn connect_socks5(proxy_addr: &str, target: &str) -> Result<TcpStream, Error> {
+    let (host, port) = split_addr(target)?;
+    let mut stream = TcpStream::connect(proxy_addr).await?;
+
+    stream.write_all(&[0x05, 0x01, 0x00]).await?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice != [0x05, 0x00] {
+        return Err(Error::new(ErrorKind::PermissionDenied, "SOCKS5 proxy requires authentication"));
+    }
+
+    let mut request = vec![0x05, 0x01, 0x00];
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        Ok(IpAddr::V6(ip)) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+        Err(_) => {
+            request.push(0x03);
+            request.push(host.len() as u8);
+            request.extend_from_slice(host.as_bytes());
+        }
+    }
+    request.extend_from_slice(&port.to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0x00 {
+        return Err(Error::new(ErrorKind::ConnectionRefused, format!("SOCKS5 reply code {}", reply[1])));
+    }
+
+    let bound_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        _ => return Err(Error::new(ErrorKind::InvalidData, "invalid SOCKS5 address type")),
+    };
+    let mut bound = vec![0u8; bound_len + 2];
+    stream.read_exact(&mut bound).await?;
+
+    Ok(stream)
+}
+
+/// Connects to `target` (`host:port`), directly or through the proxy.
+/// Once this returns, the stream behaves like a direct connection to the target.
+pub async fn connect(target: &str, proxy: Option<&Proxy>) -> Result<TcpStream, Error> {
+    match proxy {
+        None => TcpStream::connect(target).await,
+        Some(Proxy::Http(proxy_addr)) => connect_http(proxy_addr, target).await,
+        Some(Proxy::Socks5(proxy_addr)) => connect_socks5(proxy_addr, target).await,
+    }
+}
@@ -0,0 +1,264 @@
+use std::collections::{BTreeMap, HashSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use crate::scanning::tcp::get_service_name;
+use crate::scanning::types::ScanResult;
+
+// Same cap as a live banner read
+const MAX_BANNER_BYTES: usize = 1024;
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const TCP_SYN: u8 = 0x02;
+const TCP_ACK: u8 = 0x10;
+
+/// One TCP segment from the capture, reduced to what replay needs.
+struct TcpSegment {
+    src: (IpAddr, u16),
+    flags: u8,
+    seq: u32,
+    payload: Vec<u8>,
+}
+
+#[derive(Default)]
+struct Endpoint {
+    banner: Vec<u8>,
+    seen: HashSet<u32>,
+}
+
+fn u16_be(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+}
+
+fn u32_be(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+/// Strips the link layer and returns the IP packet.
+fn ip_packet(linktype: u32, frame: &[u8]) -> Option<&[u8]> {
+    match linktype {
+        LINKTYPE_ETHERNET => {
+            let mut offset = 12;
+            // 802.1Q tags put the real ethertype four bytes later
+            while u16_be(frame, offset)? == 0x8100 {
+                offset += 4;
+            }
+            frame.get(offset + 2..)
+        }
+        LINKTYPE_LINUX_SLL => frame.get(16..),
+        LINKTYPE_LINUX_SLL2 => frame.get(20..),
+        LINKTYPE_NULL => frame.get(4..),
+        LINKTYPE_RAW => Some(frame),
+        _ => None,
+    }
+}
+
+fn tcp_segment(packet: &[u8]) -> Option<TcpSegment> {
+    let (src, tcp) = match packet.first()? >> 4 {
+        4 => {
+            let header_len = (packet[0] & 0x0F) as usize * 4;
+            let total_len = (u16_be(packet, 2)? as usize).min(packet.len());
+            if *packet.get(9)? != 6 {
+                return None;
+            }
+            let src: [u8; 4] = packet.get(12..16)?.try_into().ok()?;
+            (IpAddr::V4(Ipv4Addr::from(src)), packet.get(header_len..total_len)?)
+        }
+        6 => {
+            // Extension headers are rare in scan traffic and are not followed
+            if *packet.get(6)? != 6 {
+                return None;
+            }
+            let payload_len = (u16_be(packet, 4)? as usize + 40).min(packet.len());
+            let src: [u8; 16] = packet.get(8..24)?.try_into().ok()?;
+            (IpAddr::V6(Ipv6Addr::from(src)), packet.get(40..payload_len)?)
+        }
+        _ => return None,
+    };
+
+    let data_offset = (*tcp.get(12)? >> 4) as usize * 4;
+    Some(TcpSegment {
+        src: (src, u16_be(tcp, 0)?),
+        flags: *tcp.get(13)?,
+        seq: u32_be(tcp, 4)?,
+        payload: tcp.get(data_offset..)?.to_vec(),
+    })
+}
+
+/// Reads the TCP segments of a classic pcap file, in either byte order and with
+/// microsecond or nanosecond timestamps.
+fn read_segments(capture: &[u8]) -> Result<Vec<TcpSegment>, String> {
+    let magic = capture.get(..4).ok_or("file is too short for a pcap header")?;
+    let big_endian = match magic {
+        [0xD4, 0xC3, 0xB2, 0xA1] | [0x4D, 0x3C, 0xB2, 0xA1] => false,
+        [0xA1, 0xB2, 0xC3, 0xD4] | [0xA1, 0xB2, 0x3C, 0x4D] => true,
+        [0x0A, 0x0D, 0x0D, 0x0A] => return Err("pcapng is not supported, convert it with `editcap -F pcap`".to_string()),
+        _ => return Err("not a pcap file".to_string()),
+    };
+    let read_u32 = |offset: usize| -> Option<u32> {
+        let bytes: [u8; 4] = capture.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if big_endian { u32::from_be_bytes(bytes) } else { u32::from_le_bytes(bytes) })
+    };
+
+    let linktype = read_u32(20).ok_or("file is too short for a pcap header")?;
+    if ![LINKTYPE_NULL, LINKTYPE_ETHERNET, LINKTYPE_RAW, LINKTYPE_LINUX_SLL, LINKTYPE_LINUX_SLL2].contains(&linktype) {
+        return Err(format!("unsupported link type {}", linktype));
+    }
+
+    let mut segments = Vec::new();
+    let mut offset = 24;
+    let mut record = 1;
+    while offset < capture.len() {
+        let truncated = || format!("record {} is truncated", record);
+        let captured_len = read_u32(offset + 8).ok_or_else(truncated)? as usize;
+        let start = offset + 16;
+        let frame = start.checked_add(captured_len).and_then(|end| capture.get(start..end)).ok_or_else(truncated)?;
+        if let Some(segment) = ip_packet(linktype, frame).and_then(tcp_segment) {
+            segments.push(segment);
+        }
+        offset = start + captured_len;
+        record += 1;
+    }
+    Ok(segments)
+}
+
+/// Rebuilds scan results from a capture: every SYN-ACK marks an open port, and the data
+/// that port sent afterwards is its banner. Results are grouped by host.
+pub async fn replay(path: &str) -> Result<BTreeMap<IpAddr, Vec<ScanResult>>, String> {
+    let capture = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+    let segments = read_segments(&capture).map_err(|e| format!("{}: {}", path, e))?;
+
+    let mut endpoints: BTreeMap<(IpAddr, u16), Endpoint> = BTreeMap::new();
+    for segment in segments {
+        if segment.flags & (TCP_SYN | TCP_ACK) == TCP_SYN | TCP_ACK {
+            endpoints.entry(segment.src).or_default();
+            continue;
+        }
+
+        let Some(endpoint) = endpoints.get_mut(&segment.src) else {
+            continue;
+        };
+        // Retransmissions repeat the sequence number
+        if segment.payload.is_empty() || endpoint.banner.len() >= MAX_BANNER_BYTES || !endpoint.seen.insert(segment.seq) {
+            continue;
+        }
+        let room = MAX_BANNER_BYTES - endpoint.banner.len();
+        endpoint.banner.extend(segment.payload.into_iter().take(room));
+    }
+
+    let mut hosts: BTreeMap<IpAddr, Vec<ScanResult>> = BTreeMap::new();
+    for ((host, port), endpoint) in endpoints {
+        let banner = String::from_utf8_lossy(&endpoint.banner).to_string();
+        let service = match banner.trim().is_empty() {
+            true => "Unknown".to_string(),
+            false => get_service_name(&banner),
+        };
+        hosts.entry(host).or_default().push(ScanResult::new(port, "TCP", banner, service));
+    }
+    Ok(hosts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pcap_header(linktype: u32) -> Vec<u8> {
+        let mut header = vec![0xD4, 0xC3, 0xB2, 0xA1, 2, 0, 4, 0];
+        header.extend([0; 8]);
+        header.extend(65535u32.to_le_bytes());
+        header.extend(linktype.to_le_bytes());
+        header
+    }
+
+    fn record(frame: &[u8]) -> Vec<u8> {
+        let mut record = vec![0; 8];
+        record.extend((frame.len() as u32).to_le_bytes());
+        record.extend((frame.len() as u32).to_le_bytes());
+        record.extend(frame);
+        record
+    }
+
+    /// An IPv4 TCP segment from 10.0.0.1:21 to 10.0.0.2:40000.
+    fn ipv4_tcp(flags: u8, seq: u32, payload: &[u8]) -> Vec<u8> {
+        let mut packet = vec![0x45, 0];
+        packet.extend(((40 + payload.len()) as u16).to_be_bytes());
+        packet.extend([0, 0, 0, 0, 64, 6, 0, 0, 10, 0, 0, 1, 10, 0, 0, 2]);
+        packet.extend(21u16.to_be_bytes());
+        packet.extend(40000u16.to_be_bytes());
+        packet.extend(seq.to_be_bytes());
+        packet.extend([0, 0, 0, 0, 0x50, flags, 0xFF, 0xFF, 0, 0, 0, 0]);
+        packet.extend(payload);
+        packet
+    }
+
+    fn capture() -> Vec<u8> {
+        let mut capture = pcap_header(LINKTYPE_RAW);
+        capture.extend(record(&ipv4_tcp(TCP_SYN | TCP_ACK, 1, b"")));
+        capture.extend(record(&ipv4_tcp(TCP_ACK, 2, b"220 ready\r\n")));
+        capture
+    }
+
+    #[test]
+    fn reads_the_segments_of_a_capture() {
+        let segments = read_segments(&capture()).unwrap();
+        assert_eq!(segments.len(), 2);
+        assert_eq!(segments[0].src, ("10.0.0.1".parse().unwrap(), 21));
+        assert_eq!(segments[0].flags, TCP_SYN | TCP_ACK);
+        assert_eq!(segments[1].seq, 2);
+        assert_eq!(segments[1].payload, b"220 ready\r\n");
+    }
+
+    #[test]
+    fn truncated_global_header_is_an_error() {
+        for len in [0, 3, 4, 23] {
+            assert!(read_segments(&capture()[..len]).is_err(), "{} bytes", len);
+        }
+    }
+
+    #[test]
+    fn truncated_record_is_an_error() {
+        let capture = capture();
+        let second_record = 24 + 16 + 40;
+        // Inside the record header, and inside the frame
+        for len in [second_record + 1, second_record + 15, capture.len() - 1] {
+            assert_eq!(read_segments(&capture[..len]).err(), Some("record 2 is truncated".to_string()), "{} bytes", len);
+        }
+        // A length far past the end of the file
+        let mut huge = pcap_header(LINKTYPE_RAW);
+        huge.extend([0; 8]);
+        huge.extend(u32::MAX.to_le_bytes());
+        huge.extend(u32::MAX.to_le_bytes());
+        assert!(read_segments(&huge).is_err());
+    }
+
+    #[test]
+    fn wrong_magic_is_an_error() {
+        let mut capture = capture();
+        capture[..4].copy_from_slice(b"GIF8");
+        assert_eq!(read_segments(&capture).err(), Some("not a pcap file".to_string()));
+        capture[..4].copy_from_slice(&[0x0A, 0x0D, 0x0D, 0x0A]);
+        assert!(read_segments(&capture).err().unwrap().contains("pcapng"));
+    }
+
+    #[test]
+    fn malformed_packets_are_skipped() {
+        // An Ethernet frame too short for its ethertype
+        let mut capture = pcap_header(LINKTYPE_ETHERNET);
+        capture.extend(record(&[0; 10]));
+        assert!(read_segments(&capture).unwrap().is_empty());
+
+        // An IPv4 header longer than its packet, and a TCP data offset past the end of the segment
+        let mut capture = pcap_header(LINKTYPE_RAW);
+        let mut long_header = ipv4_tcp(TCP_ACK, 1, b"");
+        long_header[0] = 0x4F;
+        capture.extend(record(&long_header));
+        let mut bad_offset = ipv4_tcp(TCP_ACK, 1, b"");
+        bad_offset[32] = 0xF0;
+        capture.extend(record(&bad_offset));
+        capture.extend(record(&[0x45]));
+        assert!(read_segments(&capture).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,681 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::IpAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use colored::Colorize;
+use futures::stream::{self, Stream};
+use regex::Regex;
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinHandle;
+use tokio::time::{self, MissedTickBehavior};
+use crate::scanning::checkpoint::ProgressDb;
+use crate::scanning::protocols::{self, ServiceProbe};
+use crate::scanning::proxy::{self, Proxy};
+use crate::scanning::tcp::{print_closed, print_result, scan_tcp, scan_udp};
+use crate::scanning::types::{ClosedReason, ScanResult};
+
+/// Delay before the first retry of a silent port, doubled for each retry after it
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+
+/// Ports scanned at the same time unless `with_concurrency` says otherwise. Well below the
+/// usual limit of 1024 open files, which also has to cover the connections probes open.
+pub const DEFAULT_CONCURRENCY: usize = 500;
+
+pub struct ScanConfig {
+    /// The target as given, an address or a CIDR block
+    pub ip: String,
+    /// Addresses the ports are scanned on, just `ip` unless a CIDR block was expanded
+    pub hosts: Vec<String>,
+    pub ports: Vec<u16>,
+    pub protocol: &'static str,
+    pub timeout: Duration,
+    pub min_banner_bytes: usize,
+    pub chunk_size: Option<usize>,
+    /// Most ports scanned at the same time
+    pub concurrency: usize,
+    /// Most connections started per second, unlimited when `None`
+    pub rate_limit: Option<u32>,
+    /// Extra attempts for a port that did not answer, see `retries`
+    pub retries: Option<u32>,
+    pub proxy: Option<Proxy>,
+    /// Payloads sent to silent services before reading their banner, keyed by port
+    pub client_first: HashMap<u16, Vec<u8>>,
+    /// Commands sent after a service's greeting, whose answer is added to the banner, keyed by port
+    pub greeting_replies: HashMap<u16, Vec<u8>>,
+    /// User-Agent sent with the HTTP probe
+    pub random_agent: Option<String>,
+    /// File the user agents are picked from, the bundled list when `None`
+    pub user_agents_path: Option<PathBuf>,
+    /// Probes that take over their ports from the built-in probes and client-first payloads
+    pub service_probes: Vec<Box<dyn ServiceProbe>>,
+    /// Only open ports whose banner matches are reported
+    pub banner_filter: Option<Regex>,
+    /// Banner text matching this is highlighted in the output
+    pub banner_highlight: Option<Regex>,
+    /// Reconnect once with a generic probe when an open port sends no banner
+    pub retry_empty_banner: bool,
+    /// Print every closed TCP port with the reason it did not connect
+    pub verbose_closed: bool,
+    /// Close scan connections with a RST instead of a FIN (SO_LINGER 0)
+    pub half_open: bool,
+    /// Every finished port is recorded here so an interrupted scan can be resumed
+    pub progress: Option<ProgressDb>,
+    /// Report the finished scan as nmap XML (`XmlWriter`) instead of text
+    pub xml_output: bool,
+}
+
+impl ScanConfig {
+    pub fn new(ip: String, ports: Vec<u16>, protocol: &'static str) -> Self {
+        ScanConfig {
+            hosts: vec![ip.clone()],
+            ip,
+            ports,
+            protocol,
+            timeout: Duration::from_secs(100),
+            min_banner_bytes: 1,
+            chunk_size: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            rate_limit: None,
+            retries: None,
+            proxy: None,
+            client_first: protocols::default_client_first(),
+            greeting_replies: protocols::default_greeting_replies(),
+            random_agent: None,
+            user_agents_path: None,
+            service_probes: Vec::new(),
+            banner_filter: None,
+            banner_highlight: None,
+            retry_empty_banner: false,
+            verbose_closed: false,
+            half_open: false,
+            progress: None,
+            xml_output: false,
+        }
+    }
+
+    /// Scans the ports on each of these hosts instead of on `ip` alone.
+    pub fn with_hosts(mut self, hosts: Vec<IpAddr>) -> Self {
+        self.hosts = hosts.iter().map(IpAddr::to_string).collect();
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn with_min_banner_bytes(mut self, min_banner_bytes: usize) -> Self {
+        self.min_banner_bytes = min_banner_bytes;
+        self
+    }
+
+    /// Scan the ports in batches of `chunk_size`, waiting for each batch before starting the next.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = Some(chunk_size);
+        self
+    }
+
+    /// Scan at most `concurrency` ports at once; every other port waits for one of them to
+    /// finish. Too many at once runs out of file descriptors, and the ports that fail to
+    /// open a socket would be reported as closed.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Start at most `packets_per_second` port scans per second. Unlike `with_chunk_size`,
+    /// this caps throughput without waiting for earlier ports to finish.
+    pub fn with_rate_limit(mut self, packets_per_second: u32) -> Self {
+        self.rate_limit = Some(packets_per_second.max(1));
+        self
+    }
+
+    /// Tries a port that timed out (TCP) or sent nothing back (UDP) up to `retries`
+    /// more times, backing off exponentially between attempts.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = Some(retries);
+        self
+    }
+
+    /// The retries set with `with_retries`, otherwise none for TCP and two for UDP, whose
+    /// first datagram is often dropped.
+    pub fn retries(&self) -> u32 {
+        self.retries.unwrap_or(match self.protocol {
+            "UDP" => 2,
+            _ => 0,
+        })
+    }
+
+    /// Route TCP connections through an HTTP CONNECT or SOCKS5 proxy.
+    pub fn with_proxy(mut self, proxy: Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Registers probes that run instead of the built-in ones on the ports they declare.
+    /// When several probes claim a port, the one registered first is used.
+    pub fn with_service_probes(mut self, probes: Vec<Box<dyn ServiceProbe>>) -> Self {
+        self.service_probes.extend(probes);
+        self
+    }
+
+    pub fn with_random_agent(mut self, user_agent: String) -> Self {
+        self.random_agent = Some(user_agent);
+        self
+    }
+
+    /// Picks the random user agent from the lines of this file instead of the bundled list.
+    pub fn with_user_agents_path(mut self, path: PathBuf) -> Self {
+        self.user_agents_path = Some(path);
+        self
+    }
+
+    pub(crate) fn service_probe(&self, port: u16) -> Option<&dyn ServiceProbe> {
+        self.service_probes
+            .iter()
+            .find(|probe| probe.matching_ports().contains(&port))
+            .map(|probe| probe.as_ref())
+    }
+
+    pub fn with_banner_filter(mut self, filter: Regex) -> Self {
+        self.banner_filter = Some(filter);
+        self
+    }
+
+    pub fn with_retry_empty_banner(mut self, retry: bool) -> Self {
+        self.retry_empty_banner = retry;
+        self
+    }
+
+    pub fn with_verbose_closed(mut self, verbose_closed: bool) -> Self {
+        self.verbose_closed = verbose_closed;
+        self
+    }
+
+    /// Resets scan connections (SO_LINGER 0) instead of closing them with a FIN. Has no effect
+    /// through a proxy.
+    pub fn with_half_open(mut self, half_open: bool) -> Self {
+        self.half_open = half_open;
+        self
+    }
+
+    pub fn with_progress_db(mut self, progress: ProgressDb) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    pub fn with_xml_output(mut self, xml_output: bool) -> Self {
+        self.xml_output = xml_output;
+        self
+    }
+
+    /// Filters on the pattern like `with_banner_filter` and highlights the matching text.
+    pub fn with_banner_grep(mut self, pattern: Regex) -> Self {
+        self.banner_filter = Some(pattern.clone());
+        self.banner_highlight = Some(pattern);
+        self
+    }
+}
+
+// Long enough for a slow route, short enough not to hold up the scan itself
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(3);
+
+// How many port ranges the summary lists before collapsing the rest into a count
+const SHOWN_PORT_RANGES: usize = 5;
+
+/// Compacts a port list into ranges, e.g. `22, 80, 8000-8100 (+47 more)`.
+fn summarize_ports(ports: &[u16]) -> String {
+    let mut sorted = ports.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut ranges: Vec<(u16, u16)> = Vec::new();
+    for port in sorted {
+        match ranges.last_mut() {
+            Some((_, end)) if *end as u32 + 1 == port as u32 => *end = port,
+            _ => ranges.push((port, port)),
+        }
+    }
+
+    let shown = ranges
+        .iter()
+        .take(SHOWN_PORT_RANGES)
+        .map(|&(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let hidden: usize = ranges.iter().skip(SHOWN_PORT_RANGES).map(|&(start, end)| (end - start) as usize + 1).sum();
+
+    match hidden {
+        0 => shown,
+        _ => format!("{} (+{} more)", shown, hidden),
+    }
+}
+
+impl fmt::Display for ScanConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.protocol, self.ip)?;
+        if self.hosts.len() > 1 {
+            write!(f, " ({} hosts)", self.hosts.len())?;
+        }
+        write!(f, ", {} ports: {}, timeout {:?}", self.ports.len(), summarize_ports(&self.ports), self.timeout)?;
+        match self.chunk_size {
+            Some(chunk_size) => write!(f, ", {} ports at a time", chunk_size)?,
+            None => write!(f, ", {} ports at a time", self.concurrency.min(self.ports.len() * self.hosts.len()))?,
+        }
+
+        let mut options = Vec::new();
+        match &self.proxy {
+            Some(Proxy::Http(addr)) => options.push(format!("HTTP proxy {}", addr)),
+            Some(Proxy::Socks5(addr)) => options.push(format!("SOCKS5 proxy {}", addr)),
+            None => {}
+        }
+        if self.min_banner_bytes != 1 {
+            options.push(format!("min banner {} bytes", self.min_banner_bytes));
+        }
+        if !self.service_probes.is_empty() {
+            options.push(format!("{} custom probes", self.service_probes.len()));
+        }
+        if let Some(filter) = &self.banner_filter {
+            options.push(format!("banner filter /{}/", filter));
+        }
+        if self.retry_empty_banner {
+            options.push("retry empty banners".to_string());
+        }
+        if self.verbose_closed {
+            options.push("verbose closed".to_string());
+        }
+        if self.half_open {
+            options.push("half-open (RST on close)".to_string());
+        }
+        if self.progress.is_some() {
+            options.push("progress recorded".to_string());
+        }
+
+        if !options.is_empty() {
+            write!(f, " ({})", options.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// What one pass over the configured ports produced.
+pub struct ScanRun {
+    pub results: Vec<ScanResult>,
+    /// Host and port of every scan task that panicked, so their state is unknown
+    pub errored_ports: Vec<(String, u16)>,
+}
+
+/// Aborts the task once dropped, instead of detaching it like a plain `JoinHandle`.
+struct AbortOnDrop<T>(JoinHandle<T>);
+
+impl<T> Drop for AbortOnDrop<T> {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+pub struct NetworkScanner {
+    config: Arc<ScanConfig>,
+}
+
+impl NetworkScanner {
+    pub fn new(config: ScanConfig) -> Self {
+        NetworkScanner { config: Arc::new(config) }
+    }
+
+    pub fn config(&self) -> &ScanConfig {
+        &self.config
+    }
+
+    /// Connects to the first port of the first host to check that the target is reachable at all.
+    /// A refused direct connection still proves the host answered. `None` when there are no ports.
+    pub async fn test_connectivity(&self) -> Option<bool> {
+        let port = *self.config.ports.first()?;
+        let addr = socket_addr(self.config.hosts.first()?, port);
+        let timeout = self.config.timeout.min(CONNECTIVITY_TIMEOUT);
+
+        Some(match tokio::time::timeout(timeout, proxy::connect(&addr, self.config.proxy.as_ref())).await {
+            Ok(Ok(_)) => true,
+            // Through a proxy, a refusal may come from the proxy rather than the target
+            Ok(Err(e)) => self.config.proxy.is_none() && e.kind() == io::ErrorKind::ConnectionRefused,
+            Err(_) => false,
+        })
+    }
+
+    /// Scans every port on every host. The open ports come back sorted by host, then port.
+    pub async fn run(&self) -> ScanRun {
+        self.scan(true).await
+    }
+
+    /// Like `run`, but prints nothing while scanning, for callers that report on the results themselves.
+    pub async fn run_silent(&self) -> ScanRun {
+        self.scan(false).await
+    }
+
+    /// Like `run_silent`, but yields each open port as soon as it is found, in the order the
+    /// ports finish, instead of collecting them first. The stream ends once every port is
+    /// scanned. Has to be called from within a Tokio runtime.
+    pub fn run_stream(&self) -> impl Stream<Item = ScanResult> {
+        let (rx, producer) = self.start(false);
+        // Dropping the stream early stops the scans that have not started yet
+        stream::unfold((rx, AbortOnDrop(producer)), |(mut rx, producer)| async move { rx.recv().await.map(|result| (result, (rx, producer))) })
+    }
+
+    /// Starts scanning in the background. Open ports arrive on the returned channel as they are
+    /// found; the task resolves to the ports whose scan task failed once every port is done.
+    fn start(&self, print: bool) -> (mpsc::Receiver<ScanResult>, JoinHandle<Vec<(String, u16)>>) {
+        let (tx, rx) = mpsc::channel(100);
+        let config = Arc::clone(&self.config);
+        if let Some(progress) = &config.progress {
+            progress.begin_run();
+        }
+
+        // Scans are started from a separate task so results can be drained while others still run
+        let producer = tokio::spawn(async move {
+            // Each running scan holds a permit; taking all of them waits for every scan to finish
+            let permits = config.concurrency.min(Semaphore::MAX_PERMITS).min(u32::MAX as usize);
+            let semaphore = Arc::new(Semaphore::new(permits));
+            let errored_ports = Arc::new(Mutex::new(Vec::new()));
+
+            // Each scan waits for a tick; skipped ticks are not made up in a burst
+            let mut ticker = config.rate_limit.map(|rate| {
+                let mut ticker = time::interval(Duration::from_secs(1) / rate);
+                ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                ticker
+            });
+
+            // Targets are generated as they are scanned, a large block has millions of them
+            let mut started = 0;
+            for host in &config.hosts {
+                for &port in &config.ports {
+                    // Ports already recorded by an earlier, interrupted run of this scan are not scanned again
+                    if config.progress.as_ref().is_some_and(|progress| progress.is_completed(host, port)) {
+                        continue;
+                    }
+                    // A chunk has to finish before the next one starts
+                    if config.chunk_size.is_some_and(|chunk_size| started > 0 && started % chunk_size == 0) {
+                        drop(semaphore.acquire_many(permits as u32).await);
+                    }
+                    if let Some(ticker) = ticker.as_mut() {
+                        ticker.tick().await;
+                    }
+                    started += 1;
+
+                    let permit = Arc::clone(&semaphore).acquire_owned().await.expect("the scan semaphore is never closed");
+                    let task = spawn_port_scan(Arc::clone(&config), host.clone(), port, print, tx.clone());
+                    let config = Arc::clone(&config);
+                    let errored_ports = Arc::clone(&errored_ports);
+                    let host = host.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = task.await {
+                            let label = endpoint_label(&config, &host, port);
+                            eprintln!("{}{} {} => {}: {}", "[ERROR]".red(), format!("[{}]", config.protocol).yellow(), label.yellow(), "Scan task failed".red(), e);
+                            errored_ports.lock().unwrap().push((host, port));
+                        }
+                        drop(permit);
+                    });
+                }
+            }
+            drop(semaphore.acquire_many(permits as u32).await);
+            if let Some(progress) = &config.progress {
+                progress.flush().await;
+            }
+
+            let mut errored_ports = std::mem::take(&mut *errored_ports.lock().unwrap());
+            errored_ports.sort();
+            errored_ports
+        });
+
+        (rx, producer)
+    }
+
+    async fn scan(&self, print: bool) -> ScanRun {
+        let (mut rx, producer) = self.start(print);
+        let mut results: Vec<ScanResult> = Vec::new();
+
+        while let Some(result) = rx.recv().await {
+            results.push(result);
+        }
+        // Tasks finish in any order; sorting by host, then port, makes repeated scans comparable
+        results.sort();
+
+        ScanRun {
+            results,
+            errored_ports: producer.await.unwrap_or_default(),
+        }
+    }
+}
+
+/// `host:port`, bracketing IPv6 hosts.
+pub(crate) fn socket_addr(host: &str, port: u16) -> String {
+    match host.contains(':') {
+        true => format!("[{}]:{}", host, port),
+        false => format!("{}:{}", host, port),
+    }
+}
+
+/// How a port is named in the output: just the number for a single host, `host:port` otherwise.
+pub fn endpoint_label(config: &ScanConfig, host: &str, port: u16) -> String {
+    match config.hosts.len() {
+        1 => port.to_string(),
+        _ => socket_addr(host, port),
+    }
+}
+
+/// Runs `attempt` until it returns `Some`, at most `retries` more times, waiting
+/// `RETRY_BACKOFF` before the first retry and twice as long before each one after it.
+async fn with_retries<T, F, Fut>(retries: u32, mut attempt: F) -> Option<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Option<T>>,
+{
+    let mut backoff = RETRY_BACKOFF;
+    for _ in 0..retries {
+        if let Some(outcome) = attempt().await {
+            return Some(outcome);
+        }
+        time::sleep(backoff).await;
+        backoff *= 2;
+    }
+    attempt().await
+}
+
+fn spawn_port_scan(config: Arc<ScanConfig>, host: String, port: u16, print: bool, tx: mpsc::Sender<ScanResult>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let result = match config.protocol {
+            // Only silence is retried, a refused or reset connection is already an answer
+            "TCP" => match with_retries(config.retries(), || async {
+                match scan_tcp(&config, &host, port).await {
+                    Err(ClosedReason::Timeout) => None,
+                    outcome => Some(outcome),
+                }
+            })
+            .await
+            .unwrap_or(Err(ClosedReason::Timeout))
+            {
+                Ok(result) => Some(result),
+                // A reset after the handshake was always reported, the other reasons only on request
+                Err(reason) => {
+                    if print && (config.verbose_closed || reason == ClosedReason::Reset) {
+                        print_closed(&endpoint_label(&config, &host, port), reason);
+                    }
+                    None
+                }
+            },
+            "UDP" => with_retries(config.retries(), || scan_udp(&host, port, config.timeout)).await,
+            _ => None,
+        };
+
+        let result = result
+            .filter(|result| config.banner_filter.as_ref().is_none_or(|filter| filter.is_match(&result.banner)))
+            .map(|result| result.with_target_ip(host.parse().ok()));
+
+        if let Some(progress) = &config.progress {
+            progress.record(&host, config.protocol, port, result.as_ref());
+        }
+
+        let Some(result) = result else {
+            return;
+        };
+
+        if print {
+            print_result(&result, config.banner_highlight.as_ref(), config.hosts.len() > 1);
+        }
+        // The receiver is gone when a stream was dropped before the scan finished
+        tx.send(result).await.ok();
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use futures::StreamExt;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+    use super::*;
+    use crate::scanning::testing::{closed_port, tcp_banner_server};
+    use crate::scanning::types::ScanSummary;
+
+    /// Listeners that count how many scan connections are open across all of them at once.
+    async fn counting_servers(count: usize, open: Arc<AtomicUsize>, most_open: Arc<AtomicUsize>) -> Vec<u16> {
+        let mut ports = Vec::new();
+        for _ in 0..count {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            ports.push(listener.local_addr().unwrap().port());
+            let (open, most_open) = (Arc::clone(&open), Arc::clone(&most_open));
+            tokio::spawn(async move {
+                while let Ok((mut stream, _)) = listener.accept().await {
+                    let (open, most_open) = (Arc::clone(&open), Arc::clone(&most_open));
+                    tokio::spawn(async move {
+                        most_open.fetch_max(open.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+                        // Hold the connection a while so the scans overlap if they are allowed to
+                        time::sleep(Duration::from_millis(100)).await;
+                        stream.write_all(b"220 ready\r\n").await.ok();
+                        let mut buffer = [0u8; 256];
+                        while matches!(stream.read(&mut buffer).await, Ok(n) if n > 0) {}
+                        open.fetch_sub(1, Ordering::SeqCst);
+                    });
+                }
+            });
+        }
+        ports
+    }
+
+    #[tokio::test]
+    async fn concurrency_limits_the_ports_scanned_at_once() {
+        let (open, most_open) = (Arc::new(AtomicUsize::new(0)), Arc::new(AtomicUsize::new(0)));
+        let ports = counting_servers(8, Arc::clone(&open), Arc::clone(&most_open)).await;
+        let config = ScanConfig::new("127.0.0.1".to_string(), ports, "TCP").with_timeout(Duration::from_secs(2)).with_concurrency(2);
+
+        let run = NetworkScanner::new(config).run_silent().await;
+
+        assert_eq!(run.results.len(), 8);
+        assert!(run.errored_ports.is_empty());
+        assert_eq!(most_open.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_paces_the_scans() {
+        let mut ports = Vec::new();
+        for _ in 0..20 {
+            ports.push(closed_port().await);
+        }
+        let config = ScanConfig::new("127.0.0.1".to_string(), ports, "TCP").with_timeout(Duration::from_secs(1)).with_rate_limit(10);
+
+        let started = time::Instant::now();
+        let run = NetworkScanner::new(config).run_silent().await;
+
+        // The first scan starts right away, the other 19 a tenth of a second apart
+        assert!(started.elapsed() >= Duration::from_millis(1800), "took {:?}", started.elapsed());
+        assert!(run.results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn run_stream_yields_open_ports_as_they_are_found() {
+        let ports = vec![tcp_banner_server(b"220 ready\r\n").await, closed_port().await, tcp_banner_server(b"SSH-2.0-OpenSSH_9.6\r\n").await];
+        let config = ScanConfig::new("127.0.0.1".to_string(), ports.clone(), "TCP").with_timeout(Duration::from_secs(2));
+
+        let mut found: Vec<u16> = NetworkScanner::new(config).run_stream().map(|result| result.port).collect().await;
+        found.sort();
+        let mut expected = vec![ports[0], ports[2]];
+        expected.sort();
+        assert_eq!(found, expected);
+    }
+
+    #[tokio::test]
+    async fn dropping_the_stream_stops_the_scan() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&accepted);
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move { stream.write_all(b"220 ready\r\n").await.ok() });
+            }
+        });
+        // 50 scans of the same port, started 20 per second
+        let config = ScanConfig::new("127.0.0.1".to_string(), vec![port; 50], "TCP").with_timeout(Duration::from_secs(2)).with_rate_limit(20);
+
+        let mut stream = Box::pin(NetworkScanner::new(config).run_stream());
+        assert_eq!(stream.next().await.map(|result| result.port), Some(port));
+        drop(stream);
+
+        // Scans already running may still connect, but no new ones start
+        time::sleep(Duration::from_millis(300)).await;
+        let after_drop = accepted.load(Ordering::SeqCst);
+        time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(accepted.load(Ordering::SeqCst), after_drop);
+        assert!(after_drop < 50);
+    }
+
+    /// A probe that panics, standing in for any bug that takes down a port's scan task.
+    struct PanickingProbe([u16; 1]);
+
+    impl ServiceProbe for PanickingProbe {
+        fn matching_ports(&self) -> &[u16] {
+            &self.0
+        }
+
+        fn probe(&self, _stream: tokio::net::TcpStream, _timeout: Duration) -> futures::future::BoxFuture<'_, protocols::ProbeResult> {
+            Box::pin(async { panic!("probe bug") })
+        }
+    }
+
+    #[tokio::test]
+    async fn panicking_port_scans_are_reported_as_errored() {
+        let broken = tcp_banner_server(b"220 ready\r\n").await;
+        let healthy = tcp_banner_server(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+        let config = ScanConfig::new("127.0.0.1".to_string(), vec![broken, healthy], "TCP")
+            .with_timeout(Duration::from_secs(2))
+            .with_service_probes(vec![Box::new(PanickingProbe([broken]))]);
+        let scanner = NetworkScanner::new(config);
+
+        let run = scanner.run_silent().await;
+
+        // The other ports are still scanned and reported
+        assert_eq!(run.results.iter().map(|result| result.port).collect::<Vec<_>>(), vec![healthy]);
+        assert_eq!(run.errored_ports, vec![("127.0.0.1".to_string(), broken)]);
+        let summary = ScanSummary::complete(scanner.config(), &run.results, run.errored_ports.len(), Duration::from_secs(1));
+        assert_eq!(summary.errored_ports, 1);
+        assert_eq!(summary.open_ports, 1);
+    }
+
+    #[test]
+    fn concurrency_defaults_to_a_bounded_number() {
+        let config = ScanConfig::new("127.0.0.1".to_string(), (1..=1024).collect(), "TCP");
+        assert_eq!(config.concurrency, DEFAULT_CONCURRENCY);
+        assert_eq!(config.with_concurrency(0).concurrency, 1);
+    }
+
+    #[test]
+    fn xml_output_is_opt_in() {
+        let config = ScanConfig::new("127.0.0.1".to_string(), vec![22], "TCP");
+        assert!(!config.xml_output);
+        assert!(config.with_xml_output(true).xml_output);
+    }
+}
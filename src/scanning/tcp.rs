@@ -1,97 +1,251 @@
-use tokio::io::{AsyncReadExt};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use std::time::Duration;
-use std::{fs};
-use std::error::Error;
+use std::fs;
+use std::path::Path;
 use colored::Colorize;
-use futures::AsyncBufReadExt;
 use regex::Regex;
-use tokio::net::{TcpStream, UdpSocket};
+use tokio::net::{UdpSocket, UnixStream};
+use tokio::sync::OnceCell;
+use crate::scanning::nmap;
+use crate::scanning::protocols::{self, ProbeTarget};
+use crate::scanning::protocols::udp_patterns::UdpResponseLibrary;
+use crate::scanning::protocols::udp_probes::UdpProbeFactory;
+use crate::scanning::proxy;
+use crate::scanning::scanner::{socket_addr, ScanConfig};
+use crate::scanning::types::{ClosedReason, ScanResult};
 
-pub(crate) async fn get_user_agents() -> Vec<String> {
-    let user_path = "user-agents.txt".to_string();
-    let user_agents = fs::read_to_string(user_path).unwrap_or("Mozilla/5.0".to_string());
-    user_agents.lines().map(|x| x.to_string()).collect()
+/// The user agents shipped with webshot, used unless `--user-agents-file` names another list.
+const BUNDLED_USER_AGENTS: &str = include_str!("user-agents.txt");
+
+static USER_AGENTS: OnceCell<Vec<String>> = OnceCell::const_new();
+
+/// Loaded on first use, from `path` or the bundled list, and shared for the rest of the
+/// process. A file that cannot be read or holds no agents falls back to the bundled list.
+pub async fn get_user_agents(path: Option<&Path>) -> &'static [String] {
+    USER_AGENTS
+        .get_or_init(|| async {
+            let parse = |content: &str| content.lines().map(str::trim).filter(|line| !line.is_empty()).map(str::to_string).collect::<Vec<_>>();
+            let custom = path.and_then(|path| match fs::read_to_string(path) {
+                Ok(content) => Some(parse(&content)).filter(|agents| !agents.is_empty()),
+                Err(e) => {
+                    let time = chrono::Local::now().format("%H:%M:%S").to_string();
+                    eprintln!("{}{} {}: {}", format!("[{}]", time).yellow(), "[WARN]".bright_yellow(), format!("Could not read {}, using the bundled user agents", path.display()).yellow(), e);
+                    None
+                }
+            });
+            custom.unwrap_or_else(|| parse(BUNDLED_USER_AGENTS))
+        })
+        .await
 }
 
-async fn get_service_name(server_response: &str) -> Result<String, Box<dyn Error>> {
-    let url = "https://svn.nmap.org/nmap/nmap-service-probes?view=co&rev=HEAD&pathrev=HEAD";
+/// Names the service behind a banner with the nmap service probes, with the product,
+/// version and details the matching line extracts.
+pub(crate) fn get_service_name(server_response: &str) -> String {
+    match nmap::fingerprint_service(server_response.as_bytes()) {
+        Some(version) => version.to_string(),
+        None => "Unknown".to_string(),
+    }
+}
 
-    let response = reqwest::get(url).await?;
-    let probe = response.text().await?;
+/// An open port and the findings its probe reported, one line each, highlighting the parts
+/// of the banner that match `highlight`.
+pub fn format_result(result: &ScanResult, highlight: Option<&Regex>, with_host: bool) -> String {
+    let protocol = format!("[{}]", result.protocol);
+    let endpoint = result.endpoint(with_host);
 
-    let match_regex = Regex::new(r"match (\S+) m\|([^|]+?)\| p/([^/]+?)/")?;
+    let mut lines = vec![if result.banner.is_empty() {
+        format!(
+            "{}{} {} => {}",
+            "[OPEN]".green(),
+            protocol.yellow(),
+            endpoint.yellow(),
+            "No Meaningful Banner".yellow()
+        )
+    } else {
+        let banner = match highlight {
+            Some(regex) => regex.replace_all(&result.banner, |caps: &regex::Captures| caps[0].bold().yellow().to_string()),
+            None => result.banner.as_str().into(),
+        };
 
-    for match_cap in match_regex.captures_iter(&probe) {
-        let service_name = &match_cap[1];
-        let pattern = &match_cap[2];
+        format!(
+            "{}{} {} => {}: {} => {}: {}",
+            "[OPEN]".green(),
+            protocol.yellow(),
+            endpoint.yellow(),
+            "Response".green(),
+            banner,
+            "Service".green(),
+            result.service
+        )
+    }];
 
-        if let Ok(regex) = Regex::new(pattern) {
-            if regex.is_match(server_response) {
-                return Ok(format!("{} {}", service_name, &match_cap[3]));
-            }
-        }
+    for finding in &result.findings {
+        lines.push(format!("{}{} {} => {}", finding.severity.label(), protocol.yellow(), endpoint.yellow(), finding.message));
     }
+    lines.join("\n")
+}
 
+/// Prints an open port as `format_result` renders it.
+pub fn print_result(result: &ScanResult, highlight: Option<&Regex>, with_host: bool) {
+    crate::status!("{}", format_result(result, highlight, with_host));
+}
 
-    Ok("Unknown".to_string())
+/// Prints a closed TCP port with the reason it did not connect.
+pub fn print_closed(endpoint: &str, reason: ClosedReason) {
+    crate::status!(
+        "{}{} {} => {}",
+        "[CLOSED]".red(),
+        "[TCP]".yellow(),
+        endpoint.yellow(),
+        reason.to_string().red()
+    );
 }
 
-pub async fn scan_tcp(ip: &str, port: u16, duration: Duration) ->  Option<(u16, String, String)> {
-    let addr = format!("{}:{}", ip, port);
+// nmap's GenericLines probe, enough to make most line based services answer with an error
+const GENERIC_PROBE: &[u8] = b"\r\n\r\n";
 
-    match tokio::time::timeout(duration, TcpStream::connect(&addr)).await {
+/// Reconnects to a port that stayed silent and reads the answer to a generic probe.
+async fn retry_with_probe(target: &ProbeTarget<'_>, buffer: &mut [u8]) -> Option<usize> {
+    let mut stream = target.connect().await?;
+    stream.write_all(GENERIC_PROBE).await.ok()?;
+    tokio::time::timeout(target.timeout, stream.read(buffer)).await.ok()?.ok()
+}
+
+pub async fn scan_tcp(config: &ScanConfig, host: &str, port: u16) -> Result<ScanResult, ClosedReason> {
+    let addr = socket_addr(host, port);
+    let duration = config.timeout;
+    let proxy = config.proxy.as_ref();
+
+    match tokio::time::timeout(duration, proxy::connect(&addr, proxy)).await {
         Ok(Ok(mut stream)) => {
+            // A zero linger makes the close send a RST, so the session never finishes cleanly
+            if config.half_open && proxy.is_none() {
+                stream.set_linger(Some(Duration::ZERO)).ok();
+            }
+
+            if let Some(service_probe) = config.service_probe(port) {
+                return Ok(match service_probe.probe(stream, duration).await {
+                    Some(probe) => ScanResult::new(port, "TCP", probe.banner, probe.service).with_findings(probe.findings),
+                    None => ScanResult::new(port, "TCP", String::new(), "Unknown".to_string()),
+                });
+            }
+
+            let target = ProbeTarget { addr: &addr, port, timeout: duration, proxy, user_agent: config.random_agent.as_deref() };
+
+            if let Some(probe) = protocols::probe_tcp(&target, &mut stream).await {
+                return Ok(ScanResult::new(port, "TCP", probe.banner, probe.service).with_findings(probe.findings));
+            }
+
+            if let Some(payload) = config.client_first.get(&port) {
+                if let Err(e) = stream.write_all(payload).await {
+                    return Err(ClosedReason::from_io(&e));
+                }
+            }
+
             let mut buffer = [0u8; 1024];
 
-            if let Ok(n) = stream.read(&mut buffer).await {
-                let response = String::from_utf8_lossy(&buffer[..n]).to_string();
-                let res_clone = response.clone();
+            // Services that wait for the client never send anything, so silence counts as an empty banner
+            let read = tokio::time::timeout(duration, stream.read(&mut buffer)).await.unwrap_or(Ok(0));
 
-                let service_name = get_service_name(res_clone.as_str());
-                let service_name_result = service_name.await.unwrap().to_string();
+            let mut n = match read {
+                Ok(n) => n,
+                Err(e) => return Err(ClosedReason::from_io(&e)),
+            };
 
-                println!(
-                    "{}{} {} => {}: {} => {}: {}",
-                    "[OPEN]".green(),
-                    "[TCP]".yellow(),
-                    port.to_string().yellow(),
-                    "Response".green(),
-                    response.clone().to_string(),
-                    "Service".green(),
-                    service_name_result
-                );
+            if n < config.min_banner_bytes && config.retry_empty_banner {
+                if let Some(retried) = retry_with_probe(&target, &mut buffer).await {
+                    n = retried;
+                }
+            }
 
-                Some((port, response, service_name_result))
-            } else {
-                println!(
-                    "{}{} {} => {}",
-                    "[CLOSED]".red(),
-                    "[TCP]".yellow(),
-                    port.to_string().yellow(),
-                    "No Response".red()
-                );
-                None
+            if n < config.min_banner_bytes {
+                return Ok(ScanResult::new(port, "TCP", String::new(), "Unknown".to_string()));
             }
+
+            // Greeting services get a command whose answer tells more than the greeting alone
+            if let Some(payload) = config.greeting_replies.get(&port) {
+                if stream.write_all(payload).await.is_ok() {
+                    if let Ok(Ok(more)) = tokio::time::timeout(duration, stream.read(&mut buffer[n..])).await {
+                        n += more;
+                    }
+                }
+            }
+
+            let response = String::from_utf8_lossy(&buffer[..n]).to_string();
+            let res_clone = response.clone();
+
+            let service_name_result = get_service_name(res_clone.as_str());
+
+            Ok(ScanResult::new(port, "TCP", response, service_name_result))
         }
-        Ok(Err(_)) => {
-            None
-        }
-        Err(e) => {
-            eprintln!("{}", e);
-            None
-        }
+        Ok(Err(e)) => Err(ClosedReason::from_io(&e)),
+        Err(_) => Err(ClosedReason::Timeout),
+    }
+}
+
+/// Banner grab on a local UNIX domain socket. Sockets that stay silent are sent an HTTP
+/// request, since most local service APIs (Docker, containerd, ...) speak HTTP.
+pub async fn scan_unix_socket(path: &str, duration: Duration) -> Option<(String, String)> {
+    let mut stream = match tokio::time::timeout(duration, UnixStream::connect(path)).await {
+        Ok(Ok(stream)) => stream,
+        _ => return None,
+    };
+
+    let mut buffer = [0u8; 1024];
+    let mut n = match tokio::time::timeout(duration, stream.read(&mut buffer)).await {
+        Ok(Ok(n)) => n,
+        Ok(Err(_)) => return None,
+        Err(_) => 0,
+    };
+
+    if n == 0 && stream.write_all(protocols::HTTP_GET).await.is_ok() {
+        n = tokio::time::timeout(duration, stream.read(&mut buffer)).await.ok()?.unwrap_or(0);
+    }
+
+    if n == 0 {
+        println!(
+            "{}{} {} => {}",
+            "[OPEN]".green(),
+            "[UNIX]".yellow(),
+            path.yellow(),
+            "No Meaningful Banner".yellow()
+        );
+        return Some((String::new(), "Unknown".to_string()));
     }
+
+    let response = String::from_utf8_lossy(&buffer[..n]).to_string();
+    let service_name = get_service_name(&response);
+
+    println!(
+        "{}{} {} => {}: {} => {}: {}",
+        "[OPEN]".green(),
+        "[UNIX]".yellow(),
+        path.yellow(),
+        "Response".green(),
+        response,
+        "Service".green(),
+        service_name
+    );
+
+    Some((response, service_name))
 }
 
-pub async fn scan_udp(ip: &str, port: u16, duration: Duration) -> Option<(u16, String, String)> {
-    let addr = format!("{}:{}", ip, port);
-    let local_addr = "0.0.0.0:0";
+pub async fn scan_udp(ip: &str, port: u16, duration: Duration) -> Option<ScanResult> {
+    let addr = socket_addr(ip, port);
+    let local_addr = if ip.contains(':') { "[::]:0" } else { "0.0.0.0:0" };
 
     match UdpSocket::bind(local_addr).await {
         Ok(socket) => {
-            let message = b"Ping";
-            if let Err(e) = socket.send_to(message, &addr).await {
-                println!(
+            let target = ProbeTarget { addr: &addr, port, timeout: duration, proxy: None, user_agent: None };
+
+            if let Some(probed) = protocols::probe_udp(&target, &socket).await {
+                let probe = probed?;
+                return Some(ScanResult::new(port, "UDP", probe.banner, probe.service).with_findings(probe.findings));
+            }
+
+            let message = UdpProbeFactory::build(port);
+            if let Err(e) = socket.send_to(&message, &addr).await {
+                eprintln!(
                     "{}{} {} => {}: {}",
                     "[ERROR]".red(),
                     "[UDP]".yellow(),
@@ -108,21 +262,12 @@ pub async fn scan_udp(ip: &str, port: u16, duration: Duration) -> Option<(u16, S
                     let response = String::from_utf8_lossy(&buffer[..n]).to_string();
                     let res_clone = response.clone();
 
-                    let service_name = get_service_name(res_clone.as_str());
-                    let ser_clone = service_name.await.unwrap().to_string();
-
-                    println!(
-                        "{}{} {} => {}: {} => {}: {}",
-                        "[OPEN]".green(),
-                        "[UDP]".yellow(),
-                        port.to_string().yellow(),
-                        "Response".green(),
-                        response,
-                        "Service".green(),
-                        ser_clone
-                    );
-
-                    Some((port, response, ser_clone))
+                    let ser_clone = match UdpResponseLibrary::builtin().identify(&buffer[..n]) {
+                        Some(service) => service.to_string(),
+                        None => get_service_name(res_clone.as_str()),
+                    };
+
+                    Some(ScanResult::new(port, "UDP", response, ser_clone))
                 }
                 _ => {
                     None
@@ -130,7 +275,7 @@ pub async fn scan_udp(ip: &str, port: u16, duration: Duration) -> Option<(u16, S
             }
         }
         Err(e) => {
-            println!(
+            eprintln!(
                 "{}{} {} => {}: {}",
                 "[ERROR]".red(),
                 "[UDP]".yellow(),
@@ -142,3 +287,58 @@ pub async fn scan_udp(ip: &str, port: u16, duration: Duration) -> Option<(u16, S
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanning::testing::{closed_port, tcp_banner_server, tcp_reply_server, udp_reply_server};
+
+    fn config(port: u16) -> ScanConfig {
+        ScanConfig::new("127.0.0.1".to_string(), vec![port], "TCP").with_timeout(Duration::from_secs(2))
+    }
+
+    #[tokio::test]
+    async fn banner_is_read_and_named() {
+        let port = tcp_banner_server(b"SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13.5\r\n").await;
+        let result = scan_tcp(&config(port), "127.0.0.1", port).await.unwrap();
+
+        assert_eq!(result.port, port);
+        assert_eq!(result.banner, "SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13.5\r\n");
+        assert!(result.service.starts_with("ssh OpenSSH 9.6p1"), "{}", result.service);
+    }
+
+    #[tokio::test]
+    async fn silent_service_is_open_without_banner() {
+        let port = tcp_reply_server(b"unused").await;
+        let result = scan_tcp(&config(port).with_timeout(Duration::from_millis(300)), "127.0.0.1", port).await.unwrap();
+
+        assert_eq!(result.banner, "");
+        assert_eq!(result.service, "Unknown");
+    }
+
+    #[tokio::test]
+    async fn client_first_payload_gets_an_answer() {
+        let port = tcp_reply_server(b"+PONG\r\n").await;
+        let mut config = config(port);
+        config.client_first.insert(port, b"PING\r\n".to_vec());
+
+        let result = scan_tcp(&config, "127.0.0.1", port).await.unwrap();
+        assert_eq!(result.banner, "+PONG\r\n");
+    }
+
+    #[tokio::test]
+    async fn closed_port_is_refused() {
+        let port = closed_port().await;
+        assert_eq!(scan_tcp(&config(port), "127.0.0.1", port).await.unwrap_err(), ClosedReason::Refused);
+    }
+
+    #[tokio::test]
+    async fn udp_reply_becomes_the_banner() {
+        let port = udp_reply_server(b"canned udp answer").await;
+        let result = scan_udp("127.0.0.1", port, Duration::from_secs(2)).await.unwrap();
+
+        assert_eq!(result.protocol, "UDP");
+        assert_eq!(result.banner, "canned udp answer");
+    }
+}
+
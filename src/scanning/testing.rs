@@ -0,0 +1,92 @@
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, UdpSocket};
+
+// In-process listeners with canned answers, so scans are tested against known responses
+// instead of whatever happens to listen on the test machine
+
+/// Accepts TCP connections on an ephemeral localhost port and sends `banner` to each one
+/// as soon as it connects. Returns the port.
+pub async fn tcp_banner_server(banner: &'static [u8]) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                stream.write_all(banner).await.ok();
+                // Keep the connection open until the scanner is done with it
+                let mut buffer = [0u8; 1024];
+                while matches!(stream.read(&mut buffer).await, Ok(n) if n > 0) {}
+            });
+        }
+    });
+    port
+}
+
+/// Like `tcp_banner_server`, but stays silent until the client sends something and
+/// answers every request with `reply`, as client-first services do.
+pub async fn tcp_reply_server(reply: &'static [u8]) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buffer = [0u8; 1024];
+                while matches!(stream.read(&mut buffer).await, Ok(n) if n > 0) {
+                    if stream.write_all(reply).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+    });
+    port
+}
+
+/// Answers every datagram sent to an ephemeral localhost UDP port with `reply`.
+pub async fn udp_reply_server(reply: &'static [u8]) -> u16 {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = socket.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut buffer = [0u8; 2048];
+        while let Ok((_, peer)) = socket.recv_from(&mut buffer).await {
+            socket.send_to(reply, peer).await.ok();
+        }
+    });
+    port
+}
+
+/// A localhost port nothing listens on: bound once to reserve a free number, then released.
+pub async fn closed_port() -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    listener.local_addr().unwrap().port()
+}
+
+/// A minimal HTTP/1.0 server: answers `GET <path>` with the raw response `routes` maps the
+/// path to, or a bare 404, then closes the connection.
+pub async fn http_server(routes: &'static [(&'static str, &'static str)]) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buffer = [0u8; 1024];
+                while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await {
+                        Ok(n) if n > 0 => request.extend_from_slice(&buffer[..n]),
+                        _ => return,
+                    }
+                }
+                let request = String::from_utf8_lossy(&request);
+                let path = request.split_whitespace().nth(1).unwrap_or("/");
+                let response = routes
+                    .iter()
+                    .find(|(route, _)| *route == path)
+                    .map(|(_, response)| *response)
+                    .unwrap_or("HTTP/1.0 404 Not Found\r\n\r\n");
+                stream.write_all(response.as_bytes()).await.ok();
+            });
+        }
+    });
+    port
+}
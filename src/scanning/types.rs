@@ -0,0 +1,429 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use colored::Colorize;
+use crate::scanning::protocols::{Finding, Severity};
+use crate::scanning::scanner::ScanConfig;
+
+#[derive(Debug, Clone)]
+pub struct ScanResult {
+    pub port: u16,
+    pub protocol: &'static str,
+    pub banner: String,
+    pub service: String,
+    pub findings: Vec<Finding>,
+    /// Host the port belongs to, set by the scanner
+    pub target_ip: Option<IpAddr>,
+}
+
+impl ScanResult {
+    pub fn new(port: u16, protocol: &'static str, banner: String, service: String) -> Self {
+        ScanResult { port, protocol, banner, service, findings: Vec::new(), target_ip: None }
+    }
+
+    pub fn with_target_ip(mut self, target_ip: Option<IpAddr>) -> Self {
+        self.target_ip = target_ip;
+        self
+    }
+
+    /// `host:port` when the host should be shown (scans of several hosts), otherwise the port.
+    pub fn endpoint(&self, with_host: bool) -> String {
+        match self.target_ip {
+            Some(ip) if with_host => SocketAddr::new(ip, self.port).to_string(),
+            _ => self.port.to_string(),
+        }
+    }
+
+    pub fn with_findings(mut self, findings: Vec<Finding>) -> Self {
+        self.findings = findings;
+        self
+    }
+
+    pub fn to_json(&self) -> serde_json::Value {
+        let findings: Vec<_> = self
+            .findings
+            .iter()
+            .map(|finding| {
+                let severity = match finding.severity {
+                    Severity::Medium => "medium",
+                    Severity::High => "high",
+                    Severity::Critical => "critical",
+                };
+                serde_json::json!({ "severity": severity, "message": finding.message })
+            })
+            .collect();
+        serde_json::json!({
+            "port": self.port,
+            "protocol": self.protocol.to_lowercase(),
+            "state": "open",
+            "service": self.service,
+            "banner": self.banner,
+            "target_ip": self.target_ip.map(|ip| ip.to_string()),
+            "findings": findings,
+        })
+    }
+
+    fn key(&self) -> (Option<IpAddr>, u16, &'static str) {
+        (self.target_ip, self.port, self.protocol)
+    }
+
+    /// Ranks how much a result tells about the service: a real banner beats an
+    /// empty one, an identified service beats "Unknown", and longer banners win ties.
+    fn information(&self) -> (bool, bool, usize) {
+        let banner = self.banner.trim();
+        (!banner.is_empty(), self.service != "Unknown", banner.len())
+    }
+}
+
+// Results are identified by (host, port, protocol): two scans of the same port compare equal
+// even when their banners differ, which is what merging and diffing rely on. Results sort by
+// host, then port, and TCP sorts before UDP.
+impl PartialEq for ScanResult {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for ScanResult {}
+
+impl Hash for ScanResult {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+impl PartialOrd for ScanResult {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ScanResult {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+/// Why a TCP port did not count as open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClosedReason {
+    /// The target answered the SYN with a RST
+    Refused,
+    /// Nothing came back before the timeout, usually a firewall dropping packets
+    Timeout,
+    /// An ICMP host or network unreachable came back
+    Unreachable,
+    /// The connection was reset after the handshake
+    Reset,
+    /// Any other connect error, e.g. from the proxy
+    Failed,
+}
+
+impl ClosedReason {
+    pub fn from_io(error: &io::Error) -> Self {
+        match error.kind() {
+            io::ErrorKind::ConnectionRefused => ClosedReason::Refused,
+            io::ErrorKind::TimedOut => ClosedReason::Timeout,
+            io::ErrorKind::HostUnreachable | io::ErrorKind::NetworkUnreachable => ClosedReason::Unreachable,
+            io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted | io::ErrorKind::BrokenPipe => ClosedReason::Reset,
+            _ => ClosedReason::Failed,
+        }
+    }
+}
+
+impl fmt::Display for ClosedReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            ClosedReason::Refused => "Connection refused",
+            ClosedReason::Timeout => "Timed out (filtered)",
+            ClosedReason::Unreachable => "Host or network unreachable",
+            ClosedReason::Reset => "Connection reset",
+            ClosedReason::Failed => "Connection failed",
+        };
+        write!(f, "{}", reason)
+    }
+}
+
+/// Combines two result sets of the same target, keeping one entry per (port, protocol).
+/// When both sides have an entry the more informative one is kept, and `a` wins ties.
+pub fn merge_results(a: Vec<ScanResult>, b: Vec<ScanResult>) -> Vec<ScanResult> {
+    let mut merged: Vec<ScanResult> = Vec::with_capacity(a.len() + b.len());
+    // Where each result's entry sits in `merged`, keeping the merge linear
+    let mut positions: HashMap<(Option<IpAddr>, u16, &'static str), usize> = HashMap::with_capacity(a.len() + b.len());
+
+    for result in a.into_iter().chain(b) {
+        match positions.get(&result.key()) {
+            Some(&index) if result.information() > merged[index].information() => merged[index] = result,
+            Some(_) => {}
+            None => {
+                positions.insert(result.key(), merged.len());
+                merged.push(result);
+            }
+        }
+    }
+
+    merged
+}
+
+/// A difference between two scans of the same target.
+#[derive(Debug, Clone)]
+pub enum ResultChange {
+    Opened(ScanResult),
+    Closed(ScanResult),
+    BannerChanged { before: ScanResult, after: ScanResult },
+}
+
+impl ResultChange {
+    pub fn result(&self) -> &ScanResult {
+        match self {
+            ResultChange::Opened(result) | ResultChange::Closed(result) => result,
+            ResultChange::BannerChanged { after, .. } => after,
+        }
+    }
+}
+
+/// Compares two result sets by (port, protocol), listing opened ports, closed ports and
+/// ports whose banner changed, in port order.
+pub fn diff_results(previous: &[ScanResult], current: &[ScanResult]) -> Vec<ResultChange> {
+    let previous_by_key: HashMap<_, &ScanResult> = previous.iter().map(|result| (result.key(), result)).collect();
+    let current_keys: HashSet<_> = current.iter().map(ScanResult::key).collect();
+    let mut changes = Vec::new();
+
+    for result in current {
+        match previous_by_key.get(&result.key()) {
+            None => changes.push(ResultChange::Opened(result.clone())),
+            Some(before) if before.banner.trim() != result.banner.trim() => {
+                changes.push(ResultChange::BannerChanged { before: (*before).clone(), after: result.clone() });
+            }
+            Some(_) => {}
+        }
+    }
+    for result in previous {
+        if !current_keys.contains(&result.key()) {
+            changes.push(ResultChange::Closed(result.clone()));
+        }
+    }
+
+    changes.sort_by(|a, b| a.result().cmp(b.result()));
+    changes
+}
+
+/// Totals of a finished scan, handed to the output writer after the last result.
+#[derive(Debug, Clone)]
+pub struct ScanSummary {
+    pub target: String,
+    pub protocol: &'static str,
+    pub ports_scanned: usize,
+    pub open_ports: usize,
+    /// Hosts the ports were scanned on, more than one for CIDR targets
+    pub hosts_scanned: usize,
+    /// Ports that could not be scanned because their task failed
+    pub errored_ports: usize,
+    pub duration: Duration,
+    /// Whether `--test-connectivity` ran before the scan
+    pub connectivity_tested: bool,
+    /// Outcome of the connectivity test, `None` when there was no port to try
+    pub connectivity_result: Option<bool>,
+}
+
+impl ScanSummary {
+    pub fn complete(config: &ScanConfig, results: &[ScanResult], errored_ports: usize, duration: Duration) -> Self {
+        ScanSummary {
+            target: config.ip.clone(),
+            protocol: config.protocol,
+            hosts_scanned: config.hosts.len(),
+            ports_scanned: config.ports.len(),
+            open_ports: results.len(),
+            errored_ports,
+            duration,
+            connectivity_tested: false,
+            connectivity_result: None,
+        }
+    }
+
+    pub fn with_connectivity(mut self, result: Option<bool>) -> Self {
+        self.connectivity_tested = true;
+        self.connectivity_result = result;
+        self
+    }
+}
+
+impl fmt::Display for ScanSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut rows = vec![
+            ("Target", self.target.clone()),
+            ("Protocol", self.protocol.to_string()),
+            ("Ports scanned", match self.hosts_scanned {
+                1 => self.ports_scanned.to_string(),
+                hosts => format!("{} on each of {} hosts", self.ports_scanned, hosts),
+            }),
+            ("Open ports", self.open_ports.to_string()),
+            ("Errored ports", self.errored_ports.to_string()),
+            ("Duration", format!("{:.2?}", self.duration)),
+        ];
+        if self.connectivity_tested {
+            let connectivity = match self.connectivity_result {
+                Some(true) => "reachable",
+                Some(false) => "unreachable",
+                None => "not tested (no ports)",
+            };
+            rows.push(("Connectivity", connectivity.to_string()));
+        }
+        let label_width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+        let value_width = rows.iter().map(|(_, value)| value.chars().count()).max().unwrap_or(0);
+        let border = |left: &str, middle: &str, right: &str| {
+            format!("{}{}{}{}{}", left, "─".repeat(label_width + 2), middle, "─".repeat(value_width + 2), right)
+        };
+
+        writeln!(f, "{}", border("┌", "┬", "┐"))?;
+        for (label, value) in &rows {
+            // Pad before colouring so escape codes do not count towards the column width
+            let label = format!("{:<width$}", label, width = label_width);
+            let value = format!("{:<width$}", value, width = value_width);
+            writeln!(f, "│ {} │ {} │", label.bold(), value.green())?;
+        }
+        write!(f, "{}", border("└", "┴", "┘"))
+    }
+}
+
+/// What the `web` subcommand learned from one port.
+#[derive(Debug, Clone)]
+pub struct WebProbeResult {
+    pub port: u16,
+    pub status: u16,
+    pub server: Option<String>,
+    pub powered_by: Option<String>,
+    pub content_type: Option<String>,
+    /// `Location` of a 3xx response
+    pub redirect: Option<String>,
+    /// Whether the port answered over TLS rather than plain HTTP
+    pub tls: bool,
+}
+
+impl WebProbeResult {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "port": self.port,
+            "status": self.status,
+            "server": self.server,
+            "powered_by": self.powered_by,
+            "content_type": self.content_type,
+            "redirect": self.redirect,
+            "tls": self.tls,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(port: u16, protocol: &'static str, banner: &str, service: &str) -> ScanResult {
+        ScanResult::new(port, protocol, banner.to_string(), service.to_string())
+    }
+
+    #[test]
+    fn merge_keeps_one_entry_per_port_and_protocol() {
+        let merged = merge_results(
+            vec![result(22, "TCP", "SSH-2.0-OpenSSH", "ssh"), result(53, "UDP", "", "Unknown")],
+            vec![result(22, "TCP", "SSH-2.0-OpenSSH", "ssh"), result(53, "TCP", "", "Unknown"), result(80, "TCP", "", "Unknown")],
+        );
+
+        let keys: Vec<(u16, &str)> = merged.iter().map(|result| (result.port, result.protocol)).collect();
+        assert_eq!(keys, vec![(22, "TCP"), (53, "UDP"), (53, "TCP"), (80, "TCP")]);
+    }
+
+    #[test]
+    fn merge_prefers_a_real_banner_over_a_port_guess() {
+        let merged = merge_results(vec![result(22, "TCP", "", "Unknown")], vec![result(22, "TCP", "SSH-2.0-OpenSSH_9.6", "ssh")]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].banner, "SSH-2.0-OpenSSH_9.6");
+
+        let merged = merge_results(vec![result(22, "TCP", "SSH-2.0-OpenSSH_9.6", "ssh")], vec![result(22, "TCP", "", "Unknown")]);
+        assert_eq!(merged[0].banner, "SSH-2.0-OpenSSH_9.6");
+    }
+
+    #[test]
+    fn merge_prefers_an_identified_service_then_the_longer_banner() {
+        let merged = merge_results(vec![result(25, "TCP", "220 ready", "Unknown")], vec![result(25, "TCP", "220 ok", "smtp")]);
+        assert_eq!(merged[0].service, "smtp");
+
+        let merged = merge_results(vec![result(25, "TCP", "220 ok", "smtp")], vec![result(25, "TCP", "220 mail.example.com ESMTP", "smtp")]);
+        assert_eq!(merged[0].banner, "220 mail.example.com ESMTP");
+    }
+
+    #[test]
+    fn merge_keeps_the_first_side_on_ties() {
+        let merged = merge_results(vec![result(21, "TCP", "220 first", "ftp")], vec![result(21, "TCP", "220 other", "ftp")]);
+        assert_eq!(merged[0].banner, "220 first");
+    }
+
+    #[test]
+    fn merge_tells_hosts_apart() {
+        let on = |ip: &str| result(22, "TCP", "SSH-2.0", "ssh").with_target_ip(ip.parse().ok());
+        let merged = merge_results(vec![on("10.0.0.1")], vec![on("10.0.0.2"), on("10.0.0.1")]);
+        assert_eq!(merged.len(), 2);
+    }
+
+    fn describe(changes: &[ResultChange]) -> Vec<String> {
+        changes
+            .iter()
+            .map(|change| match change {
+                ResultChange::Opened(result) => format!("opened {}/{}", result.port, result.protocol),
+                ResultChange::Closed(result) => format!("closed {}/{}", result.port, result.protocol),
+                ResultChange::BannerChanged { before, after } => format!("changed {}/{} {} -> {}", after.port, after.protocol, before.banner, after.banner),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn diff_lists_opened_closed_and_changed_ports_in_port_order() {
+        let previous = vec![
+            result(80, "TCP", "nginx/1.24", "http"),
+            result(22, "TCP", "SSH-2.0-OpenSSH_9.6", "ssh"),
+            result(21, "TCP", "220 ftp", "ftp"),
+        ];
+        let current = vec![
+            result(443, "TCP", "", "Unknown"),
+            result(22, "TCP", "SSH-2.0-OpenSSH_9.7", "ssh"),
+            result(80, "TCP", "nginx/1.24", "http"),
+        ];
+
+        assert_eq!(
+            describe(&diff_results(&previous, &current)),
+            vec!["closed 21/TCP", "changed 22/TCP SSH-2.0-OpenSSH_9.6 -> SSH-2.0-OpenSSH_9.7", "opened 443/TCP"]
+        );
+    }
+
+    #[test]
+    fn diff_ignores_surrounding_whitespace_in_banners() {
+        let previous = vec![result(25, "TCP", "220 mail ESMTP\r\n", "smtp")];
+        let current = vec![result(25, "TCP", "220 mail ESMTP", "smtp")];
+        assert!(diff_results(&previous, &current).is_empty());
+    }
+
+    #[test]
+    fn diff_keys_on_host_and_protocol_as_well_as_port() {
+        let host = |ip: &str| Some(ip.parse::<IpAddr>().unwrap());
+        let previous = vec![result(53, "UDP", "", "Unknown").with_target_ip(host("10.0.0.1"))];
+        let current = vec![
+            result(53, "TCP", "", "Unknown").with_target_ip(host("10.0.0.1")),
+            result(53, "UDP", "", "Unknown").with_target_ip(host("10.0.0.2")),
+        ];
+
+        let mut changes = describe(&diff_results(&previous, &current));
+        changes.sort();
+        assert_eq!(changes, vec!["closed 53/UDP", "opened 53/TCP", "opened 53/UDP"]);
+    }
+
+    #[test]
+    fn diff_of_identical_scans_is_empty() {
+        let results = vec![result(22, "TCP", "SSH-2.0-OpenSSH_9.6", "ssh"), result(161, "UDP", "", "snmp")];
+        assert!(diff_results(&results, &results).is_empty());
+        assert!(diff_results(&[], &[]).is_empty());
+    }
+}
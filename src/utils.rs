@@ -0,0 +1,45 @@
+use chrono::{Datelike, NaiveDate};
+
+const DEFAULT_BANNER: &str = r#"
+                 _         _           _
+ __      __ ___ | |__  ___| |__   ___ | |_
+ \ \ /\ / // _ \| '_ \/ __| '_ \ / _ \| __|
+  \ V  V /|  __/| |_) \__ \ | | | (_) | |_
+   \_/\_/  \___||_.__/|___/_| |_|\___/ \__|
+"#;
+
+const PIRATE_BANNER: &str = r#"
+              |    |    |
+             )_)  )_)  )_)
+            )___))___))___)\
+           )____)____)_____)\\
+         _____|____|____|____\\\__
+ --------\     webshot, arr!     /--------
+   ^^^^^ ^^^^^^^^^^^^^^^^^^^^^^^^^ ^^^^^
+"#;
+
+const SNOWFLAKE_BANNER: &str = r#"
+        .      *      .
+     *    \  \ | /  /    *
+      ----- webshot -----
+     *    /  / | \  \    *
+        .      *      .
+"#;
+
+const SPIDER_WEB_BANNER: &str = r#"
+   \  |  /        |
+  --  +  --   \   |   /
+   /  |  \    -- (oo) --
+  webshot     /  /||\  \
+"#;
+
+/// Picks the ASCII art shown by `--print-ascii-art` for the given day.
+pub fn select_banner(now: NaiveDate) -> &'static str {
+    match (now.month(), now.day()) {
+        // Talk Like a Pirate Day
+        (9, 19) => PIRATE_BANNER,
+        (10, _) => SPIDER_WEB_BANNER,
+        (12, _) => SNOWFLAKE_BANNER,
+        _ => DEFAULT_BANNER,
+    }
+}
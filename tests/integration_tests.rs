@@ -0,0 +1,335 @@
+use std::process::Output;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio::process::Command;
+use webshot::scanning::scanner::{NetworkScanner, ScanConfig};
+
+// End-to-end scans, through the binary or the library, against in-process listeners on localhost
+
+/// Accepts TCP connections on an ephemeral localhost port and greets each one with `banner`.
+async fn banner_server(banner: &'static [u8]) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                stream.write_all(banner).await.ok();
+                let mut buffer = [0u8; 1024];
+                while matches!(stream.read(&mut buffer).await, Ok(n) if n > 0) {}
+            });
+        }
+    });
+    port
+}
+
+/// Like `banner_server`, but waits `delay` before greeting, so scans finish out of port order.
+async fn slow_banner_server(banner: &'static [u8], delay: Duration) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                tokio::time::sleep(delay).await;
+                stream.write_all(banner).await.ok();
+                let mut buffer = [0u8; 1024];
+                while matches!(stream.read(&mut buffer).await, Ok(n) if n > 0) {}
+            });
+        }
+    });
+    port
+}
+
+async fn webshot(args: &[&str]) -> Output {
+    let output = Command::new(env!("CARGO_BIN_EXE_webshot"))
+        .args(args)
+        .env("NO_COLOR", "1")
+        .output()
+        .await
+        .unwrap();
+    assert!(output.status.success(), "webshot {:?} failed: {}", args, String::from_utf8_lossy(&output.stderr));
+    output
+}
+
+/// Just enough of an XML parser to check that output is well formed and to look at its
+/// elements: no namespaces, CDATA or entities beyond the predefined and numeric ones.
+mod xml {
+    #[derive(Debug)]
+    pub struct Element {
+        pub name: String,
+        pub attributes: Vec<(String, String)>,
+        pub children: Vec<Element>,
+    }
+
+    impl Element {
+        pub fn attribute(&self, name: &str) -> Option<&str> {
+            self.attributes.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str())
+        }
+
+        /// Every element below this one with the given name, in document order.
+        pub fn descendants<'a>(&'a self, name: &str) -> Vec<&'a Element> {
+            let mut found = Vec::new();
+            for child in &self.children {
+                if child.name == name {
+                    found.push(child);
+                }
+                found.extend(child.descendants(name));
+            }
+            found
+        }
+    }
+
+    fn unescape(value: &str) -> Result<String, String> {
+        let mut unescaped = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find('&') {
+            unescaped.push_str(&rest[..start]);
+            let end = rest[start..].find(';').ok_or_else(|| format!("unterminated entity in {:?}", value))? + start;
+            let entity = &rest[start + 1..end];
+            let c = match entity {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                _ => entity
+                    .strip_prefix("#x")
+                    .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                    .or_else(|| entity.strip_prefix('#').and_then(|decimal| decimal.parse().ok()))
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("unknown entity &{};", entity))?,
+            };
+            unescaped.push(c);
+            rest = &rest[end + 1..];
+        }
+        unescaped.push_str(rest);
+        Ok(unescaped)
+    }
+
+    fn parse_tag(tag: &str) -> Result<Element, String> {
+        let (name, mut rest) = tag.split_once(char::is_whitespace).unwrap_or((tag, ""));
+        let mut attributes = Vec::new();
+        loop {
+            rest = rest.trim_start();
+            if rest.is_empty() {
+                break;
+            }
+            let (key, value) = rest.split_once("=\"").ok_or_else(|| format!("bad attribute in <{}>", tag))?;
+            let end = value.find('"').ok_or_else(|| format!("unterminated attribute in <{}>", tag))?;
+            if value[..end].contains('<') {
+                return Err(format!("unescaped < in <{}>", tag));
+            }
+            attributes.push((key.trim().to_string(), unescape(&value[..end])?));
+            rest = &value[end + 1..];
+        }
+        Ok(Element { name: name.to_string(), attributes, children: Vec::new() })
+    }
+
+    /// Parses a document with a single root element, skipping the declaration and doctype.
+    pub fn parse(document: &str) -> Result<Element, String> {
+        let mut open: Vec<Element> = Vec::new();
+        let mut root = None;
+        let mut rest = document;
+        while let Some(start) = rest.find('<') {
+            let text = &rest[..start];
+            if !text.trim().is_empty() && open.is_empty() {
+                return Err(format!("text outside the root element: {:?}", text.trim()));
+            }
+            unescape(text)?;
+            let end = rest[start..].find('>').ok_or("unterminated tag")? + start;
+            let tag = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+
+            if tag.starts_with('?') || tag.starts_with('!') {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                let element = open.pop().ok_or_else(|| format!("</{}> without an open element", name))?;
+                if element.name != name.trim() {
+                    return Err(format!("</{}> closes <{}>", name, element.name));
+                }
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None if root.is_none() => root = Some(element),
+                    None => return Err("more than one root element".to_string()),
+                }
+                continue;
+            }
+
+            let (tag, self_closing) = match tag.strip_suffix('/') {
+                Some(tag) => (tag, true),
+                None => (tag, false),
+            };
+            let element = parse_tag(tag.trim())?;
+            match (self_closing, open.last_mut()) {
+                (false, _) => open.push(element),
+                (true, Some(parent)) => parent.children.push(element),
+                (true, None) if root.is_none() => root = Some(element),
+                (true, None) => return Err("more than one root element".to_string()),
+            }
+        }
+        if !rest.trim().is_empty() {
+            return Err(format!("text after the root element: {:?}", rest.trim()));
+        }
+        match open.pop() {
+            Some(element) => Err(format!("<{}> is never closed", element.name)),
+            None => root.ok_or_else(|| "no root element".to_string()),
+        }
+    }
+}
+
+#[tokio::test]
+async fn xml_on_stdout_is_the_only_thing_there() {
+    let port = banner_server(b"SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13\r\n").await;
+    let output = webshot(&["127.0.0.1", &port.to_string(), "--timeout", "2", "--xml"]).await;
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let document = xml::parse(&stdout).unwrap_or_else(|e| panic!("stdout is not XML ({}):\n{}", e, stdout));
+    assert_eq!(document.name, "nmaprun");
+
+    let ports = document.descendants("port");
+    assert_eq!(ports.len(), 1);
+    assert_eq!(ports[0].attribute("portid"), Some(port.to_string().as_str()));
+    assert_eq!(ports[0].descendants("state")[0].attribute("state"), Some("open"));
+
+    // The notices and the scan log moved to stderr
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("Scan completed"));
+}
+
+#[tokio::test]
+async fn repeated_scans_return_ports_in_the_same_order() {
+    // Later listeners answer sooner, so the scans complete in a different order than the ports
+    let mut ports = Vec::new();
+    for delay in (0..6).rev() {
+        ports.push(slow_banner_server(b"220 ready\r\n", Duration::from_millis(50 * delay)).await);
+    }
+    let scanner = NetworkScanner::new(ScanConfig::new("127.0.0.1".to_string(), ports.clone(), "TCP").with_timeout(Duration::from_secs(2)));
+
+    let first: Vec<u16> = scanner.run_silent().await.results.iter().map(|result| result.port).collect();
+    let second: Vec<u16> = scanner.run_silent().await.results.iter().map(|result| result.port).collect();
+
+    ports.sort();
+    assert_eq!(first, ports);
+    assert_eq!(second, first);
+}
+
+#[tokio::test]
+async fn xml_describes_services_and_os_guesses() {
+    let ssh = banner_server(b"SSH-2.0-OpenSSH_9.6p1 Ubuntu-3ubuntu13\r\n").await;
+    let other = banner_server(b"<hello> \"odd\" & unknown\r\n").await;
+    let ports = format!("{},{}", ssh, other);
+    let output = webshot(&["127.0.0.1", &ports, "--timeout", "2", "--xml"]).await;
+
+    let document = xml::parse(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let port = |number: u16| {
+        document
+            .descendants("port")
+            .into_iter()
+            .find(|port| port.attribute("portid") == Some(number.to_string().as_str()))
+            .unwrap_or_else(|| panic!("port {} is missing", number))
+    };
+
+    let service = &port(ssh).descendants("service")[0];
+    assert_eq!(service.attribute("name"), Some("ssh"));
+    assert_eq!(service.attribute("product"), Some("OpenSSH"));
+    assert_eq!(service.attribute("version"), Some("9.6p1 Ubuntu 3ubuntu13"));
+    assert_eq!(service.attribute("ostype"), Some("Linux"));
+    assert_eq!(service.attribute("method"), Some("probed"));
+
+    // Banners come back exactly as the service sent them, markup characters included
+    let banner = &port(other).descendants("script")[0];
+    assert_eq!(banner.attribute("output"), Some("<hello> \"odd\" & unknown\r\n"));
+    assert_eq!(port(other).descendants("service")[0].attribute("method"), Some("table"));
+
+    let osmatch = document.descendants("osmatch");
+    assert_eq!(osmatch.len(), 1);
+    assert_eq!(osmatch[0].attribute("name"), Some("Linux"));
+    assert_eq!(osmatch[0].attribute("accuracy"), Some("100"));
+}
+
+#[tokio::test]
+async fn csv_on_stdout_round_trips_through_a_csv_reader() {
+    let ssh = banner_server(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+    let quoted = banner_server(b"220 \"mail\", ready\r\n").await;
+    let mut ports = [ssh, quoted];
+    ports.sort();
+    let output = webshot(&["127.0.0.1", &format!("{},{}", ssh, quoted), "--timeout", "2", "--csv"]).await;
+
+    let mut reader = csv::Reader::from_reader(output.stdout.as_slice());
+    let header: Vec<String> = reader.headers().unwrap().iter().map(str::to_string).collect();
+    assert_eq!(header, ["port", "protocol", "state", "service", "banner", "target_ip", "hostname", "timestamp"]);
+
+    let rows: Vec<csv::StringRecord> = reader.records().collect::<Result<_, _>>().unwrap();
+    assert_eq!(rows.iter().map(|row| row[0].parse::<u16>().unwrap()).collect::<Vec<_>>(), ports);
+    for row in &rows {
+        assert_eq!((&row[1], &row[2], &row[5]), ("tcp", "open", "127.0.0.1"));
+    }
+    // Commas, quotes and line breaks in banners survive the quoting
+    let banner = |port: u16| rows.iter().find(|row| row[0] == *port.to_string()).unwrap()[4].to_string();
+    assert_eq!(banner(quoted), "220 \"mail\", ready\r\n");
+    assert_eq!(banner(ssh), "SSH-2.0-OpenSSH_9.6\r\n");
+}
+
+#[tokio::test]
+async fn csv_cannot_be_combined_with_xml() {
+    let output = Command::new(env!("CARGO_BIN_EXE_webshot")).args(["127.0.0.1", "80", "--csv", "--xml"]).env("NO_COLOR", "1").output().await.unwrap();
+
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--csv cannot be combined with --xml or --ports-csv"));
+}
+
+#[tokio::test]
+async fn json_flag_is_rejected_for_scans() {
+    let output = Command::new(env!("CARGO_BIN_EXE_webshot")).args(["127.0.0.1", "80", "--json"]).env("NO_COLOR", "1").output().await.unwrap();
+
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--json only applies to the web subcommand"));
+}
+
+#[tokio::test]
+async fn explicit_flags_override_the_profile() {
+    let port = banner_server(b"220 ready\r\n").await;
+    let output = webshot(&["127.0.0.1", &port.to_string(), "--profile", "web", "--timeout", "2"]).await;
+
+    // The web profile would scan its own ports with a 10 second timeout
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let plan = stdout.lines().find(|line| line.contains("Scan plan")).unwrap_or_else(|| panic!("no scan plan in:\n{}", stdout));
+    assert!(plan.contains(&format!("1 ports: {}, timeout 2s", port)), "{}", plan);
+}
+
+#[tokio::test]
+async fn text_output_file_lists_every_open_port() {
+    let ssh = banner_server(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+    let ftp = banner_server(b"220 ready\r\n").await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.txt");
+    let output = webshot(&["127.0.0.1", &format!("{},{}", ssh, ftp), "--timeout", "2", "-o", path.to_str().unwrap()]).await;
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    for port in [ssh, ftp] {
+        assert_eq!(text.lines().filter(|line| line.starts_with(&format!("[OPEN][TCP] {} => ", port))).count(), 1, "{}", text);
+    }
+    assert!(!text.contains('\x1b'));
+    // Notices and live results go to stderr, stdout stays empty
+    assert!(output.stdout.is_empty(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert!(String::from_utf8_lossy(&output.stderr).contains(&format!("[OPEN][TCP] {} => ", ssh)));
+}
+
+#[tokio::test]
+async fn json_output_file_holds_the_summary_and_results() {
+    let ssh = banner_server(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("out.json");
+    webshot(&["127.0.0.1", &ssh.to_string(), "--timeout", "2", "-o", path.to_str().unwrap()]).await;
+
+    let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(json["target"], "127.0.0.1");
+    assert_eq!((json["ports_scanned"].as_u64(), json["open_ports"].as_u64(), json["errored_ports"].as_u64()), (Some(1), Some(1), Some(0)));
+    let results = json["results"].as_array().unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["port"], ssh);
+    assert_eq!(results[0]["protocol"], "tcp");
+    assert_eq!(results[0]["banner"], "SSH-2.0-OpenSSH_9.6\r\n");
+    assert_eq!(results[0]["target_ip"], "127.0.0.1");
+}